@@ -0,0 +1,82 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use wasm_raytracer::{
+    AnySampler, HaltonSampler, IndependentSampler, Renderer, Sampler, SamplerKind, SobolSampler,
+    StratifiedSampler, Vec3,
+};
+
+// How random directions used to be drawn: a generator seeded from the
+// thread-local one for every vector, and rejection sampling on top.
+fn reseeded_unit_vector() -> Vec3 {
+    loop {
+        let mut rng = SmallRng::from_rng(rand::thread_rng()).unwrap();
+        let p = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        );
+        if p.length_squared() < 1.0 {
+            return p.unit();
+        }
+    }
+}
+
+fn unit_vectors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("unit_vector");
+    group.bench_function("reseeded_per_call", |b| {
+        b.iter(|| black_box(reseeded_unit_vector()))
+    });
+    group.bench_function("sampler", |b| {
+        let mut sampler = IndependentSampler::new(0);
+        b.iter(|| black_box(Vec3::random_unit_vector(&mut sampler)))
+    });
+    group.finish();
+}
+
+const KINDS: [(&str, SamplerKind); 4] = [
+    ("independent", SamplerKind::Independent),
+    ("stratified", SamplerKind::Stratified),
+    ("halton", SamplerKind::Halton),
+    ("sobol", SamplerKind::Sobol),
+];
+
+// A camera sample's worth of dimensions, from a sampler made for it as the
+// renderer makes them.
+fn samplers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("camera_sample");
+    for (name, kind) in KINDS {
+        group.bench_function(name, |b| {
+            let mut sample = 0;
+            b.iter(|| {
+                sample += 1;
+                let mut sampler = match kind {
+                    SamplerKind::Independent => {
+                        AnySampler::Independent(IndependentSampler::new(sample as u64))
+                    }
+                    SamplerKind::Stratified => {
+                        AnySampler::Stratified(StratifiedSampler::new(0, sample, 64))
+                    }
+                    SamplerKind::Halton => AnySampler::Halton(HaltonSampler::new(0, sample)),
+                    SamplerKind::Sobol => AnySampler::Sobol(SobolSampler::new(0, sample)),
+                };
+                for _ in 0..16 {
+                    black_box(sampler.get_2d());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render_random_scene_64x36");
+    for (name, kind) in KINDS {
+        let mut renderer = Renderer::random(64, 36, 0);
+        renderer.set_sampler(kind, 64);
+        group.bench_function(name, |b| b.iter(|| renderer.accumulate(1)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, unit_vectors, samplers, render);
+criterion_main!(benches);
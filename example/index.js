@@ -2,18 +2,87 @@ import * as Comlink from "comlink";
 
 const WIDTH = 400;
 const HEIGHT = 225;
+const TILE_SIZE = 32;
+const SAMPLES_PER_PASS = 1;
+const MAX_SAMPLES = 500;
 
 const canvas = document.getElementById("raytracer-canvas");
 canvas.width = WIDTH;
 canvas.height = HEIGHT;
 
+function tiles() {
+  const out = [];
+  for (let y = 0; y < HEIGHT; y += TILE_SIZE) {
+    for (let x = 0; x < WIDTH; x += TILE_SIZE) {
+      out.push({
+        x,
+        y,
+        w: Math.min(TILE_SIZE, WIDTH - x),
+        h: Math.min(TILE_SIZE, HEIGHT - y),
+      });
+    }
+  }
+  return out;
+}
+
+function download(bytes, name) {
+  const link = document.createElement("a");
+  link.href = URL.createObjectURL(new Blob([bytes]));
+  link.download = name;
+  link.click();
+  URL.revokeObjectURL(link.href);
+}
+
+function offerDownloads(workers) {
+  const ctx = canvas.getContext("2d");
+  for (const format of ["png", "ppm"]) {
+    document.getElementById(`download-${format}`).onclick = async () => {
+      const { data } = ctx.getImageData(0, 0, WIDTH, HEIGHT);
+      const bytes = await workers[0].encode(format, WIDTH, HEIGHT, data);
+      download(bytes, `render.${format}`);
+    };
+  }
+
+  // Only a single worker holds the linear colours of every tile.
+  if (workers.length === 1) {
+    const exr = document.getElementById("download-exr");
+    exr.hidden = false;
+    exr.onclick = async () => download(await workers[0].exr(), "render.exr");
+  }
+}
+
 (async function init() {
   if (window.Worker) {
-    const rt = Comlink.wrap(
-      new Worker(new URL("./worker.js", import.meta.url))
+    const seed = Math.floor(Math.random() * 2 ** 32);
+    const spawn = () =>
+      Comlink.wrap(new Worker(new URL("./worker.js", import.meta.url)));
+
+    // A parallel build already uses every core from one worker.
+    let workers = [spawn()];
+    if (!(await workers[0].init(WIDTH, HEIGHT, seed))) {
+      const more = Array.from(
+        { length: (navigator.hardwareConcurrency || 4) - 1 },
+        spawn
+      );
+      await Promise.all(more.map((rt) => rt.init(WIDTH, HEIGHT, seed)));
+      workers = workers.concat(more);
+    }
+    offerDownloads(workers);
+
+    // Each worker keeps refining its own share of the tiles, so a noisy
+    // image shows up straight away.
+    const ctx = canvas.getContext("2d");
+    const all = tiles();
+    await Promise.all(
+      workers.map(async (rt, i) => {
+        const mine = all.filter((_, t) => t % workers.length === i);
+        for (let samples = 0; samples < MAX_SAMPLES; samples += SAMPLES_PER_PASS) {
+          for (const tile of mine) {
+            const pixels = await rt.renderTile(tile, SAMPLES_PER_PASS);
+            ctx.putImageData(new ImageData(pixels, tile.w), tile.x, tile.y);
+          }
+        }
+      })
     );
-    const img = await rt.render(WIDTH, HEIGHT);
-    const imageData = new ImageData(img, WIDTH);
-    canvas.getContext("2d").putImageData(imageData, 0, 0);
   }
 })();
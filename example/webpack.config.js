@@ -16,4 +16,12 @@ module.exports = {
   experiments: {
     asyncWebAssembly: true,
   },
+  // SharedArrayBuffer, and so the `parallel` build's thread pool, is only
+  // available to cross-origin isolated pages.
+  devServer: {
+    headers: {
+      "Cross-Origin-Opener-Policy": "same-origin",
+      "Cross-Origin-Embedder-Policy": "require-corp",
+    },
+  },
 };
@@ -1,10 +1,43 @@
 import * as Comlink from "comlink";
 
+let wasm = null;
+let renderer = null;
+
 const raytracer = {
-  async render(width, height) {
-    const { render } = await import("wasm-raytracer");
-    const rendered = render(width, height);
-    return Comlink.transfer(rendered, [rendered.buffer]);
+  // Every worker is given the same seed, so they all build the same scene.
+  // Resolves to true when the package was built with the `parallel` feature
+  // and this worker renders on a thread pool of its own.
+  async init(width, height, seed) {
+    wasm = await import("wasm-raytracer");
+    let parallel = false;
+    if (wasm.initThreadPool && self.crossOriginIsolated) {
+      await wasm.initThreadPool(navigator.hardwareConcurrency);
+      parallel = true;
+    }
+    renderer = wasm.Renderer.random(width, height, seed);
+    return parallel;
+  },
+
+  // Adds `samples` samples per pixel to a tile and returns its pixels.
+  renderTile({ x, y, w, h }, samples) {
+    const pixels = renderer.render_tile(x, y, w, h, samples);
+    return Comlink.transfer(pixels, [pixels.buffer]);
+  },
+
+  // Encodes the RGBA pixels of the whole canvas as a "png" or "ppm" file.
+  encode(format, width, height, rgba) {
+    const bytes =
+      format === "png"
+        ? wasm.png_from_rgba(width, height, rgba)
+        : wasm.ppm_from_rgba(width, height, rgba, false);
+    return Comlink.transfer(bytes, [bytes.buffer]);
+  },
+
+  // This worker's linear image as an OpenEXR file. Only the whole image when
+  // this is the only worker.
+  exr() {
+    const bytes = renderer.to_exr();
+    return Comlink.transfer(bytes, [bytes.buffer]);
   },
 };
 
@@ -0,0 +1,136 @@
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    min: Point3,
+    max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Self { min, max }
+    }
+
+    // A box that contains nothing and is never hit; the identity for `surrounding`.
+    pub fn empty() -> Self {
+        Self {
+            min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    // Widens any axis thinner than `delta`, so flat primitives such as
+    // axis-aligned triangles still have a box the slab test can hit.
+    pub fn padded(&self, delta: f64) -> Self {
+        let extent = self.max - self.min;
+        let pad = |e: f64| if e < delta { delta / 2.0 } else { 0.0 };
+        let pad = Vec3::new(pad(extent.x()), pad(extent.y()), pad(extent.z()));
+        Self {
+            min: self.min - pad,
+            max: self.max + pad,
+        }
+    }
+
+    // Whether the box contains nothing, as it does when nothing was put in it.
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.min[axis] > self.max[axis])
+    }
+
+    pub fn min(&self) -> Point3 {
+        self.min
+    }
+
+    pub fn max(&self) -> Point3 {
+        self.max
+    }
+
+    pub fn surrounding(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.max - self.min;
+        if extent.x() < 0.0 || extent.y() < 0.0 || extent.z() < 0.0 {
+            return 0.0;
+        }
+        2.0 * (extent.x() * extent.y() + extent.y() * extent.z() + extent.z() * extent.x())
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        let dir = r.direction();
+        let inv_dir = Vec3::new(1.0 / dir.x(), 1.0 / dir.y(), 1.0 / dir.z());
+        self.hit_inv(r.origin(), inv_dir, t_min, t_max)
+    }
+
+    // Slab test with the reciprocal direction precomputed, for traversals that
+    // test one ray against many boxes.
+    pub fn hit_inv(&self, org: Point3, inv_dir: Vec3, mut t_min: f64, mut t_max: f64) -> bool {
+        for axis in 0..3 {
+            let inv_d = inv_dir[axis];
+            let mut t0 = (self.min[axis] - org[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - org[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Aabb;
+    use crate::ray::Ray;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn test_hit() {
+        let bbox = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let toward = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let away = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0));
+        let beside = Ray::new(Point3::new(2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(bbox.hit(&toward, 0.001, f64::INFINITY));
+        assert!(!bbox.hit(&away, 0.001, f64::INFINITY));
+        assert!(!bbox.hit(&beside, 0.001, f64::INFINITY));
+        assert!(!bbox.hit(&toward, 0.001, 3.0));
+    }
+
+    #[test]
+    fn test_empty() {
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        assert!(!Aabb::empty().hit(&r, f64::NEG_INFINITY, f64::INFINITY));
+        assert!(Aabb::empty().is_empty());
+
+        let bbox = Aabb::new(Point3::new(-1.0, 0.0, 0.0), Point3::new(0.0, 2.0, 1.0));
+        assert_eq!(Aabb::empty().surrounding(&bbox), bbox);
+        assert!(!bbox.is_empty());
+        let point = Point3::new(1.0, 1.0, 1.0);
+        assert!(!Aabb::new(point, point).is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::image::Image;
+
+// Images handed over by the page, which scene descriptions refer to by name.
+static IMAGES: Mutex<BTreeMap<String, Arc<Image>>> = Mutex::new(BTreeMap::new());
+
+pub fn register_image(name: &str, image: Image) {
+    IMAGES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), Arc::new(image));
+}
+
+pub fn image(name: &str) -> Option<Arc<Image>> {
+    IMAGES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
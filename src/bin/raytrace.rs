@@ -0,0 +1,435 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+use std::{env, fs};
+
+use wasm_raytracer::{
+    decode_hdr, decode_image, encode_exr, encode_png, encode_ppm, register_image, Adaptive,
+    EncodeError, FilterKind, IntegratorKind, Renderer, SamplerKind, Scene, ToneMap,
+};
+
+const USAGE: &str = "\
+usage: raytrace [options] SCENE.json
+
+Renders a JSON scene description to a PNG, PPM or OpenEXR file.
+
+options:
+  -o, --output PATH     where to write the image, .png, .ppm or .exr (default out.png)
+  --width N             image width in pixels (default 400)
+  --height N            image height in pixels (default 225)
+  --samples N           samples per pixel (default: the scene's setting)
+  --max-depth N         maximum bounces per path (default: the scene's setting)
+  --min-bounces N       bounces before Russian roulette may end a path
+                        (default: the scene's setting)
+  --seed N              seed for the random numbers (default 0)
+  --integrator NAME     path, whitted, ambient_occlusion, or a debug view of
+                        normals, depth, uv, material_id or albedo (default
+                        path)
+  --ao-distance D       how far ambient occlusion looks for surfaces in the
+                        way (default 1)
+  --sampler NAME        independent, stratified, halton or sobol
+                        (default: the scene's setting)
+  --filter NAME         box, tent, gaussian, mitchell or lanczos
+                        (default: the scene's setting)
+  --filter-radius R     how many pixels the filter reaches (default: the
+                        scene's setting)
+  --tone-map NAME       clamp, reinhard, extended_reinhard or aces
+                        (default: the scene's setting)
+  --exposure STOPS      brighten or darken the image (default: the scene's
+                        setting)
+  --adaptive ERROR      keep sampling only the pixels whose relative error is
+                        above ERROR, in passes of --samples
+  --max-samples N       the most samples an adaptive pixel takes (default
+                        1024)
+  --heatmap PATH        also write a PNG of how many samples each pixel took
+  --image NAME=PATH     load a PNG, JPEG or Radiance HDR image for the scene
+                        to refer to as NAME; may be repeated
+  -h, --help            show this message";
+
+struct Args {
+    scene: PathBuf,
+    output: PathBuf,
+    width: u16,
+    height: u16,
+    samples: Option<u32>,
+    max_depth: Option<u16>,
+    min_bounces: Option<u16>,
+    seed: u64,
+    integrator: Option<IntegratorKind>,
+    ao_distance: Option<f64>,
+    sampler: Option<SamplerKind>,
+    filter: Option<FilterKind>,
+    filter_radius: Option<f64>,
+    tone_map: Option<ToneMap>,
+    exposure: Option<f64>,
+    adaptive: Option<f64>,
+    max_samples: Option<u32>,
+    heatmap: Option<PathBuf>,
+    images: Vec<(String, PathBuf)>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut scene = None;
+    let mut output = PathBuf::from("out.png");
+    let (mut width, mut height) = (400, 225);
+    let (mut samples, mut max_depth, mut min_bounces, mut seed) = (None, None, None, 0);
+    let (mut integrator, mut ao_distance) = (None, None);
+    let (mut sampler, mut filter, mut filter_radius) = (None, None, None);
+    let (mut tone_map, mut exposure) = (None, None);
+    let (mut adaptive, mut max_samples, mut heatmap) = (None, None, None);
+    let mut images = vec![];
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => output = PathBuf::from(value()?),
+            "--width" => width = number(&arg, &value()?)?,
+            "--height" => height = number(&arg, &value()?)?,
+            "--samples" => samples = Some(number(&arg, &value()?)?),
+            "--max-depth" => max_depth = Some(number(&arg, &value()?)?),
+            "--min-bounces" => min_bounces = Some(number(&arg, &value()?)?),
+            "--seed" => seed = number(&arg, &value()?)?,
+            "--integrator" => {
+                let name = value()?;
+                integrator = Some(
+                    IntegratorKind::from_name(&name)
+                        .ok_or(format!("unknown integrator \"{name}\""))?,
+                );
+            }
+            "--ao-distance" => {
+                let distance = value()?;
+                ao_distance = Some(distance.parse::<f64>().ok().filter(|d| *d > 0.0).ok_or(
+                    format!("--ao-distance expects a positive number, got \"{distance}\""),
+                )?);
+            }
+            "--sampler" => {
+                let name = value()?;
+                sampler = Some(
+                    SamplerKind::from_name(&name).ok_or(format!("unknown sampler \"{name}\""))?,
+                );
+            }
+            "--filter" => {
+                let name = value()?;
+                filter =
+                    Some(FilterKind::from_name(&name).ok_or(format!("unknown filter \"{name}\""))?);
+            }
+            "--filter-radius" => {
+                let radius = value()?;
+                filter_radius = Some(
+                    radius
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|r| *r > 0.0 && r.is_finite())
+                        .ok_or(format!(
+                            "--filter-radius expects a positive number, got \"{radius}\""
+                        ))?,
+                );
+            }
+            "--tone-map" => {
+                let name = value()?;
+                tone_map =
+                    Some(ToneMap::from_name(&name).ok_or(format!("unknown tone map \"{name}\""))?);
+            }
+            "--exposure" => {
+                let stops = value()?;
+                exposure = Some(
+                    stops
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|e| e.is_finite())
+                        .ok_or(format!("--exposure expects a number, got \"{stops}\""))?,
+                );
+            }
+            "--adaptive" => {
+                let error = value()?;
+                adaptive = Some(
+                    error
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|e| *e > 0.0 && e.is_finite())
+                        .ok_or(format!(
+                            "--adaptive expects a positive number, got \"{error}\""
+                        ))?,
+                );
+            }
+            "--max-samples" => max_samples = Some(number(&arg, &value()?)?),
+            "--heatmap" => heatmap = Some(PathBuf::from(value()?)),
+            "--image" => {
+                let spec = value()?;
+                let (name, path) = spec
+                    .split_once('=')
+                    .ok_or(format!("--image expects NAME=PATH, got \"{spec}\""))?;
+                images.push((name.to_string(), PathBuf::from(path)));
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option {flag}")),
+            path if scene.is_none() => scene = Some(PathBuf::from(path)),
+            path => return Err(format!("unexpected argument \"{path}\"")),
+        }
+    }
+
+    if width == 0 || height == 0 {
+        return Err("the image must be at least one pixel wide and high".to_string());
+    }
+    // Scenes are checked for these, but flags override them after that.
+    if samples == Some(0) {
+        return Err("--samples must be at least 1".to_string());
+    }
+    if max_depth == Some(0) {
+        return Err("--max-depth must be at least 1".to_string());
+    }
+
+    Ok(Some(Args {
+        scene: scene.ok_or("no scene file given")?,
+        output,
+        width,
+        height,
+        samples,
+        max_depth,
+        min_bounces,
+        seed,
+        integrator,
+        ao_distance,
+        sampler,
+        filter,
+        filter_radius,
+        tone_map,
+        exposure,
+        adaptive,
+        max_samples,
+        heatmap,
+        images,
+    }))
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects a non-negative whole number, got \"{value}\""))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn run(args: Args) -> Result<(), String> {
+    // The CLI calls the `encode_*` functions directly, since the `Renderer::to_*`
+    // exports return `JsValue` errors.
+    let encode: fn(&Renderer) -> Result<Vec<u8>, EncodeError> =
+        match args.output.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => |r| encode_png(r.width(), r.height(), &r.image()),
+            Some("ppm") => |r| encode_ppm(r.width(), r.height(), &r.image()),
+            Some("exr") => |r| encode_exr(r.width(), r.height(), &r.linear()),
+            _ => {
+                return Err(format!(
+                    "{}: the output must end in .png, .ppm or .exr",
+                    args.output.display()
+                ))
+            }
+        };
+
+    for (name, path) in &args.images {
+        let bytes = read(path)?;
+        let image = if path.extension().is_some_and(|ext| ext == "hdr") {
+            decode_hdr(&bytes).map_err(|e| e.to_string())
+        } else {
+            decode_image(&bytes).map_err(|e| e.to_string())
+        };
+        register_image(name, image.map_err(|e| format!("{}: {e}", path.display()))?);
+    }
+
+    let json = String::from_utf8(read(&args.scene)?)
+        .map_err(|e| format!("{}: {e}", args.scene.display()))?;
+    let mut scene =
+        Scene::from_json(&json).map_err(|e| format!("{}: {e}", args.scene.display()))?;
+    if let Some(max_depth) = args.max_depth {
+        scene.settings.max_depth = max_depth;
+    }
+    if let Some(min_bounces) = args.min_bounces {
+        scene.settings.min_bounces = min_bounces;
+    }
+    if let Some(sampler) = args.sampler {
+        scene.settings.sampler = sampler;
+    }
+    if let Some(filter) = args.filter {
+        scene.settings.filter.kind = filter;
+    }
+    if let Some(radius) = args.filter_radius {
+        scene.settings.filter.radius = radius;
+    }
+    if let Some(tone_map) = args.tone_map {
+        scene.settings.tone_mapping.operator = tone_map;
+    }
+    if let Some(exposure) = args.exposure {
+        scene.settings.tone_mapping.exposure = exposure;
+    }
+    if let Some(threshold) = args.adaptive {
+        let adaptive = scene
+            .settings
+            .adaptive
+            .get_or_insert_with(Adaptive::default);
+        adaptive.threshold = threshold;
+    }
+    if let Some(max_samples) = args.max_samples {
+        let adaptive = scene
+            .settings
+            .adaptive
+            .get_or_insert_with(Adaptive::default);
+        adaptive.max_samples = max_samples;
+    }
+    if let Some(adaptive) = scene.settings.adaptive {
+        adaptive
+            .check()
+            .map_err(|(field, reason)| format!("adaptive {field} {reason}"))?;
+    }
+    // --samples overrides the scene's sample count.
+    if let Some(samples) = args.samples {
+        scene.settings.samples_per_pixel = samples;
+    }
+    let samples = scene.settings.samples_per_pixel;
+
+    let start = Instant::now();
+    let mut renderer = Renderer::with_scene(scene, args.width, args.height, args.seed);
+    if let Some(integrator) = args.integrator {
+        renderer.set_integrator(integrator);
+    }
+    if let Some(distance) = args.ao_distance {
+        renderer.set_ao_distance(distance);
+    }
+    if renderer.adaptive().is_some() {
+        // Passes of at least one sample, until every pixel has converged.
+        while renderer.active_pixels() > 0 {
+            renderer.accumulate(samples.max(1));
+        }
+        eprintln!(
+            "rendered {}x{} adaptively at {} to {} samples per pixel in {:.2?}",
+            args.width,
+            args.height,
+            renderer.samples(),
+            renderer.most_samples(),
+            start.elapsed()
+        );
+    } else {
+        renderer.accumulate(samples);
+        eprintln!(
+            "rendered {}x{} at {samples} samples per pixel in {:.2?}",
+            args.width,
+            args.height,
+            start.elapsed()
+        );
+    }
+
+    if let Some(path) = &args.heatmap {
+        let png =
+            encode_png(args.width, args.height, &renderer.heatmap()).map_err(|e| e.to_string())?;
+        fs::write(path, png).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    let bytes = encode(&renderer).map_err(|e| e.to_string())?;
+    fs::write(&args.output, bytes).map_err(|e| format!("{}: {e}", args.output.display()))
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => match run(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("raytrace: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Ok(None) => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("raytrace: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_args;
+    use wasm_raytracer::{FilterKind, IntegratorKind, SamplerKind, ToneMap};
+
+    fn parse(args: &[&str]) -> Result<Option<super::Args>, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&[
+            "scene.json",
+            "--width",
+            "64",
+            "--seed",
+            "9",
+            "--min-bounces",
+            "0",
+            "--integrator",
+            "material_id",
+            "--image",
+            "sky=sky.hdr",
+            "--sampler",
+            "sobol",
+            "--filter",
+            "mitchell",
+            "--tone-map",
+            "aces",
+            "--exposure",
+            "-1.5",
+            "--adaptive",
+            "0.01",
+            "--heatmap",
+            "spp.png",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.scene.to_str(), Some("scene.json"));
+        assert_eq!((args.width, args.height, args.seed), (64, 225, 9));
+        assert_eq!((args.samples, args.min_bounces), (None, Some(0)));
+        assert_eq!(args.images[0].0, "sky");
+        assert_eq!(args.sampler, Some(SamplerKind::Sobol));
+        assert_eq!(
+            (args.integrator, args.ao_distance),
+            (Some(IntegratorKind::MaterialId), None)
+        );
+        assert_eq!(
+            (args.filter, args.filter_radius),
+            (Some(FilterKind::Mitchell), None)
+        );
+        assert_eq!(
+            (args.tone_map, args.exposure),
+            (Some(ToneMap::Aces), Some(-1.5))
+        );
+
+        assert_eq!((args.adaptive, args.max_samples), (Some(0.01), None));
+        assert_eq!(args.heatmap.unwrap().to_str(), Some("spp.png"));
+
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert_eq!(parse(&[]).err().unwrap(), "no scene file given");
+        assert_eq!(
+            parse(&["s.json", "--samples", "-1"]).err().unwrap(),
+            "--samples expects a non-negative whole number, got \"-1\""
+        );
+        assert_eq!(
+            parse(&["s.json", "--samples", "0"]).err().unwrap(),
+            "--samples must be at least 1"
+        );
+        assert_eq!(
+            parse(&["s.json", "--max-depth", "0"]).err().unwrap(),
+            "--max-depth must be at least 1"
+        );
+        assert_eq!(
+            parse(&["s.json", "--seed"]).err().unwrap(),
+            "--seed needs a value"
+        );
+        assert!(parse(&["s.json", "--image", "sky"]).is_err());
+        assert!(parse(&["s.json", "--tone-map", "filmic"]).is_err());
+        assert!(parse(&["s.json", "--integrator", "photon"]).is_err());
+        assert!(parse(&["s.json", "--ao-distance", "0"]).is_err());
+        assert!(parse(&["s.json", "--filter-radius", "0"]).is_err());
+        assert!(parse(&["s.json", "--adaptive", "-0.1"]).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hittable, HittableList, Intersection};
+use crate::ray::Ray;
+
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    right: Box<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> Self {
+        Self::build(list.into_objects())
+    }
+
+    fn build(mut objects: Vec<Box<dyn Hittable>>) -> Self {
+        let bbox = objects.iter().fold(Aabb::empty(), |acc, obj| {
+            acc.surrounding(&obj.bounding_box())
+        });
+
+        let (left, right): (Box<dyn Hittable>, Box<dyn Hittable>) = match objects.len() {
+            0 => (Box::new(HittableList::new()), Box::new(HittableList::new())),
+            1 => (objects.remove(0), Box::new(HittableList::new())),
+            2 => {
+                let right = objects.remove(1);
+                (objects.remove(0), right)
+            }
+            n => {
+                // Split at the median centroid along the axis where the centroids spread most.
+                let axis = objects
+                    .iter()
+                    .fold(Aabb::empty(), |acc, obj| {
+                        let c = obj.bounding_box().centroid();
+                        acc.surrounding(&Aabb::new(c, c))
+                    })
+                    .longest_axis();
+                objects.sort_by(|a, b| {
+                    let (a, b) = (a.bounding_box().centroid(), b.bounding_box().centroid());
+                    a[axis].total_cmp(&b[axis])
+                });
+
+                let rest = objects.split_off(n / 2);
+                (Box::new(Self::build(objects)), Box::new(Self::build(rest)))
+            }
+        };
+
+        Self { left, right, bbox }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        if !self.bbox.hit(r, t_min, t_max) {
+            return None;
+        }
+
+        let left = self.left.hit(r, t_min, t_max);
+        let t_closest = left.as_ref().map_or(t_max, |i| i.t);
+        self.right.hit(r, t_min, t_closest).or(left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.bbox.hit(r, t_min, t_max)
+            && (self.left.occluded(r, t_min, t_max) || self.right.occluded(r, t_min, t_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::BvhNode;
+    use crate::hittable::{Hittable, HittableList};
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn scene() -> HittableList {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        )));
+        for _ in 0..200 {
+            let center = Point3::new(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(0.0..3.0),
+                rng.gen_range(-10.0..10.0),
+            );
+            let radius = rng.gen_range(0.1..0.8);
+            world.add(Box::new(Sphere::new(
+                center,
+                radius,
+                Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+            )));
+        }
+        world
+    }
+
+    #[test]
+    fn test_hit_matches_list() {
+        let list = scene();
+        let bvh = BvhNode::new(scene());
+        assert_eq!(bvh.bounding_box(), list.bounding_box());
+
+        let mut rng = SmallRng::seed_from_u64(7);
+        for _ in 0..2000 {
+            let r = Ray::new(
+                Point3::new(
+                    rng.gen_range(-15.0..15.0),
+                    rng.gen_range(0.5..8.0),
+                    rng.gen_range(-15.0..15.0),
+                ),
+                Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                ),
+            );
+
+            let (want, got) = (
+                list.hit(&r, 0.001, f64::INFINITY),
+                bvh.hit(&r, 0.001, f64::INFINITY),
+            );
+            assert_eq!(want.is_some(), got.is_some());
+            if let (Some(want), Some(got)) = (want, got) {
+                assert_eq!(want.t, got.t);
+                assert_eq!(want.p, got.p);
+                assert_eq!(want.normal, got.normal);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let bvh = BvhNode::new(HittableList::new());
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(bvh.hit(&r, 0.001, f64::INFINITY).is_none());
+    }
+}
@@ -1,6 +1,32 @@
+use crate::aabb::Aabb;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{Point3, Vec3};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSettings {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64, // vertical field-of-view in degrees
+    pub aperture: f64,
+    pub focus_dist: f64,
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+    }
+}
+
 pub struct Camera {
     org: Point3,
     lower_left_corner: Point3,
@@ -8,7 +34,6 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
-    w: Vec3,
     lens_radius: f64,
 }
 
@@ -43,13 +68,38 @@ impl Camera {
             vertical,
             u,
             v,
-            w,
             lens_radius: aperture / 2.0,
         }
     }
 
-    pub fn ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    // A camera looking at the whole of `bbox` from the front, slightly above
+    // and to the right, or `None` if the box is empty.
+    pub fn framing(bbox: &Aabb, aspect_ratio: f64) -> Option<Self> {
+        if bbox.is_empty() {
+            return None;
+        }
+        let vfov: f64 = 30.0;
+        let center = bbox.centroid();
+        let radius = 0.5 * (bbox.max() - bbox.min()).length();
+        // Fit the bounding sphere to the narrower of the two fields of view.
+        let h = (vfov.to_radians() / 2.0).tan();
+        let half_fov = h.min(aspect_ratio * h).atan();
+        let distance = 1.1 * radius / half_fov.sin();
+
+        let lookfrom = center + distance * Vec3::new(0.4, 0.3, 1.0).unit();
+        Some(Self::new(
+            lookfrom,
+            center,
+            Vec3::new(0.0, 1.0, 0.0),
+            vfov,
+            aspect_ratio,
+            0.0,
+            distance,
+        ))
+    }
+
+    pub fn ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * Vec3::random_in_unit_disk(sampler);
         let offset = self.u * rd.x() + self.v * rd.y();
 
         Ray::new(
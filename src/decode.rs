@@ -0,0 +1,185 @@
+use std::fmt;
+
+use crate::image::Image;
+use crate::vec3::Color;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeError(String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not decode image: {}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = b"\xff\xd8\xff";
+
+// Decodes a PNG or JPEG file, told apart by their signatures. Both store
+// sRGB-encoded colours, which are converted to linear light here so textures
+// can be lit and blended before the gamma is put back on output.
+pub fn decode_image(bytes: &[u8]) -> Result<Image, DecodeError> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        decode_png(bytes)
+    } else if bytes.starts_with(JPEG_SIGNATURE) {
+        decode_jpeg(bytes)
+    } else {
+        Err(DecodeError("not a PNG or JPEG file".to_string()))
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Image, DecodeError> {
+    let error = |e: png::DecodingError| DecodeError(e.to_string());
+
+    let mut decoder = png::Decoder::new(bytes);
+    // Palettes and low bit depths become 8-bit grey or RGB, with or without
+    // alpha.
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(error)?;
+
+    let channels = info.color_type.samples();
+    let sample: fn(&[u8]) -> f64 = match info.bit_depth {
+        png::BitDepth::Sixteen => |b| u16::from_be_bytes([b[0], b[1]]) as f64 / 65535.0,
+        _ => |b| b[0] as f64 / 255.0,
+    };
+    let bytes_per_sample = if info.bit_depth == png::BitDepth::Sixteen {
+        2
+    } else {
+        1
+    };
+
+    let pixels = buf[..info.buffer_size()]
+        .chunks_exact(channels * bytes_per_sample)
+        .map(|px| {
+            let c = |i: usize| srgb_to_linear(sample(&px[i * bytes_per_sample..]));
+            // Grey images, with or without alpha, have one colour channel.
+            if channels < 3 {
+                let grey = c(0);
+                Color::new(grey, grey, grey)
+            } else {
+                Color::new(c(0), c(1), c(2))
+            }
+        })
+        .collect();
+
+    Image::new(info.width as usize, info.height as usize, pixels)
+        .map_err(|e| DecodeError(e.to_string()))
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<Image, DecodeError> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let data = decoder.decode().map_err(|e| DecodeError(e.to_string()))?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| DecodeError("missing JPEG header".to_string()))?;
+
+    let byte = |b: u8| srgb_to_linear(b as f64 / 255.0);
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => data
+            .iter()
+            .map(|&l| Color::new(byte(l), byte(l), byte(l)))
+            .collect(),
+        jpeg_decoder::PixelFormat::L16 => data
+            .chunks_exact(2)
+            .map(|l| {
+                let l = srgb_to_linear(u16::from_be_bytes([l[0], l[1]]) as f64 / 65535.0);
+                Color::new(l, l, l)
+            })
+            .collect(),
+        jpeg_decoder::PixelFormat::RGB24 => data
+            .chunks_exact(3)
+            .map(|c| Color::new(byte(c[0]), byte(c[1]), byte(c[2])))
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => data
+            .chunks_exact(4)
+            .map(|c| {
+                let k = 1.0 - c[3] as f64 / 255.0;
+                let ink = |i: usize| srgb_to_linear((1.0 - c[i] as f64 / 255.0) * k);
+                Color::new(ink(0), ink(1), ink(2))
+            })
+            .collect(),
+    };
+
+    Image::new(info.width as usize, info.height as usize, pixels)
+        .map_err(|e| DecodeError(e.to_string()))
+}
+
+// The sRGB transfer function, undone.
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_image, srgb_to_linear};
+    use crate::vec3::Color;
+
+    fn png(color_type: png::ColorType, bit_depth: png::BitDepth, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        let mut encoder = png::Encoder::new(&mut out, 2, 1);
+        encoder.set_color(color_type);
+        encoder.set_depth(bit_depth);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn test_srgb_to_linear() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-12);
+        assert!((srgb_to_linear(0.5) - 0.21404).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_png() {
+        let rgba = png(
+            png::ColorType::Rgba,
+            png::BitDepth::Eight,
+            &[255, 0, 0, 255, 0, 255, 255, 0],
+        );
+        let img = decode_image(&rgba).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 1));
+        assert_eq!(img.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(img.pixel(1, 0), Color::new(0.0, 1.0, 1.0));
+
+        let grey = png(
+            png::ColorType::Grayscale,
+            png::BitDepth::Sixteen,
+            &[0, 0, 0xff, 0xff],
+        );
+        let img = decode_image(&grey).unwrap();
+        assert_eq!(img.pixel(1, 0), Color::new(1.0, 1.0, 1.0));
+
+        let bits = png(
+            png::ColorType::Grayscale,
+            png::BitDepth::One,
+            &[0b0100_0000],
+        );
+        let img = decode_image(&bits).unwrap();
+        assert_eq!(img.pixel(0, 0), Color::default());
+        assert_eq!(img.pixel(1, 0), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            decode_image(b"GIF89a").err().unwrap().to_string(),
+            "could not decode image: not a PNG or JPEG file"
+        );
+        assert!(decode_image(b"\xff\xd8\xff\xe0 truncated").is_err());
+
+        let mut truncated = png(png::ColorType::Rgb, png::BitDepth::Eight, &[0; 6]);
+        truncated.truncate(40);
+        assert!(decode_image(&truncated).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Piecewise-constant distributions for drawing samples in proportion to a
+// tabulated function, such as the brightness of an image.
+
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    // A function that is zero everywhere is sampled uniformly.
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len() as f64;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.0);
+        for f in &func {
+            cdf.push(cdf[cdf.len() - 1] + f.max(0.0) / n);
+        }
+
+        let integral = cdf[cdf.len() - 1];
+        if integral > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= integral);
+        } else {
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, c)| *c = i as f64 / n);
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.func.len()
+    }
+
+    // Maps `u` in [0, 1) to a point in [0, 1), returning it with its density
+    // and the index of the segment it fell in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let offset = (self.cdf.partition_point(|&c| c <= u) - 1).min(self.len() - 1);
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+
+        let x = ((offset as f64 + du) / self.len() as f64).min(1.0 - f64::EPSILON);
+        (x, self.density(offset), offset)
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.density(((x * self.len() as f64) as usize).min(self.len() - 1))
+    }
+
+    fn density(&self, offset: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[offset].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+// A distribution over the unit square, given as a grid of rows, sampled by
+// picking a row and then a column within it.
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(width: usize, func: &[f64]) -> Self {
+        let rows: Vec<_> = func
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|r| r.integral).collect());
+        Self { rows, marginal }
+    }
+
+    // Returns the point (u, v) and its density.
+    pub fn sample(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample(u.1);
+        let (u, pdf_u, _) = self.rows[row].sample(u.0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, (u, v): (f64, f64)) -> f64 {
+        let row = ((v * self.rows.len() as f64) as usize).min(self.rows.len() - 1);
+        self.marginal.pdf(v) * self.rows[row].pdf(u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Distribution1D, Distribution2D};
+
+    #[test]
+    fn test_1d() {
+        let d = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+
+        assert_eq!(d.sample(0.0), (0.0, 0.5, 0));
+        assert_eq!(d.sample(0.3125), (0.375, 1.5, 1));
+        // The zero segment is never chosen.
+        assert_eq!(d.sample(0.5).2, 3);
+        assert_eq!(d.pdf(0.6), 0.0);
+        assert_eq!(d.pdf(0.9), 2.0);
+
+        let zero = Distribution1D::new(vec![0.0, 0.0]);
+        assert_eq!(zero.sample(0.75), (0.75, 1.0, 1));
+    }
+
+    #[test]
+    fn test_2d_pdf_matches_samples() {
+        let func = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let d = Distribution2D::new(3, &func);
+
+        for &u in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.99)] {
+            let (p, pdf) = d.sample(u);
+            assert!((d.pdf(p) - pdf).abs() < 1e-12);
+            // Density relative to the mean value of the grid.
+            let cell = (p.1 * 3.0) as usize * 3 + (p.0 * 3.0) as usize;
+            assert!((pdf - func[cell] / 4.0).abs() < 1e-12);
+        }
+    }
+}
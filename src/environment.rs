@@ -0,0 +1,236 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::distribution::Distribution2D;
+use crate::image::Image;
+use crate::vec3::{Color, Vec3};
+
+// A direction towards the environment chosen by `Environment::sample`, with
+// the light arriving from it and its probability density per solid angle.
+pub struct EnvironmentSample {
+    pub dir: Vec3,
+    pub radiance: Color,
+    pub pdf: f64,
+}
+
+// What a ray sees when it escapes the scene.
+pub trait Environment: Send + Sync {
+    fn color(&self, dir: Vec3) -> Color;
+
+    // Environments with small bright regions can pick directions in
+    // proportion to their brightness, so the renderer can aim shadow rays at
+    // them. The others are only found by rays bouncing off surfaces.
+    fn sample(&self, _u: (f64, f64)) -> Option<EnvironmentSample> {
+        None
+    }
+
+    // The density `sample` picks `dir` with.
+    fn pdf(&self, _dir: Vec3) -> f64 {
+        0.0
+    }
+
+    // Whether `sample` ever picks a direction.
+    fn is_sampled(&self) -> bool {
+        false
+    }
+}
+
+pub struct SolidEnvironment {
+    color: Color,
+}
+
+impl SolidEnvironment {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Environment for SolidEnvironment {
+    fn color(&self, _: Vec3) -> Color {
+        self.color
+    }
+}
+
+// A vertical blend between a colour at and below the horizon and one
+// straight overhead.
+pub struct GradientEnvironment {
+    horizon: Color,
+    zenith: Color,
+}
+
+impl GradientEnvironment {
+    pub fn new(horizon: Color, zenith: Color) -> Self {
+        Self { horizon, zenith }
+    }
+
+    pub fn horizon(&self) -> Color {
+        self.horizon
+    }
+
+    pub fn zenith(&self) -> Color {
+        self.zenith
+    }
+}
+
+impl Default for GradientEnvironment {
+    fn default() -> Self {
+        Self::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Environment for GradientEnvironment {
+    fn color(&self, dir: Vec3) -> Color {
+        let unit_dir = dir.unit();
+        let t = 0.5 * (unit_dir.y() + 1.0);
+        (1.0 - t) * self.horizon + t * self.zenith
+    }
+}
+
+// A latitude-longitude image wrapped around the scene, with +y at the top
+// row and -z at the centre column. Directions are sampled by the luminance
+// of the pixels, weighted by the solid angle each row covers.
+pub struct EquirectEnvironment {
+    image: Arc<Image>,
+    distribution: Distribution2D,
+}
+
+impl EquirectEnvironment {
+    pub fn new(image: Arc<Image>) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let weights: Vec<f64> = (0..height)
+            .flat_map(|y| {
+                let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+                let image = &image;
+                (0..width).map(move |x| image.pixel(x, y).luminance() * sin_theta)
+            })
+            .collect();
+
+        Self {
+            distribution: Distribution2D::new(width, &weights),
+            image,
+        }
+    }
+}
+
+impl Environment for EquirectEnvironment {
+    fn color(&self, dir: Vec3) -> Color {
+        let (u, v) = direction_to_uv(dir);
+        let x = ((u * self.image.width() as f64) as usize).min(self.image.width() - 1);
+        let y = ((v * self.image.height() as f64) as usize).min(self.image.height() - 1);
+        self.image.pixel(x, y)
+    }
+
+    fn sample(&self, u: (f64, f64)) -> Option<EnvironmentSample> {
+        let (uv, pdf_uv) = self.distribution.sample(u);
+        let sin_theta = (uv.1 * PI).sin();
+        if pdf_uv == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+
+        let dir = uv_to_direction(uv);
+        Some(EnvironmentSample {
+            dir,
+            radiance: self.color(dir),
+            pdf: pdf_uv / (2.0 * PI * PI * sin_theta),
+        })
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let uv = direction_to_uv(dir);
+        let sin_theta = (uv.1 * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn is_sampled(&self) -> bool {
+        true
+    }
+}
+
+// Equirectangular image coordinates of a direction, both in [0, 1].
+pub fn direction_to_uv(dir: Vec3) -> (f64, f64) {
+    let d = dir.unit();
+    let u = 0.5 + d.x().atan2(-d.z()) / (2.0 * PI);
+    let v = d.y().clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+// The unit direction at equirectangular image coordinates (u, v).
+pub fn uv_to_direction((u, v): (f64, f64)) -> Vec3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    Vec3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        direction_to_uv, uv_to_direction, Environment, EquirectEnvironment, GradientEnvironment,
+    };
+    use crate::image::Image;
+    use crate::vec3::{Color, Vec3};
+
+    #[test]
+    fn test_gradient() {
+        let env = GradientEnvironment::default();
+        assert_eq!(
+            env.color(Vec3::new(0.0, 1.0, 0.0)),
+            Color::new(0.5, 0.7, 1.0)
+        );
+        assert_eq!(
+            env.color(Vec3::new(0.0, -3.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_equirect_lookup() {
+        // Four columns by two rows, with -z in the middle of the image.
+        let pixels = (0..8).map(|i| Color::new(i as f64, 0.0, 0.0)).collect();
+        let env = EquirectEnvironment::new(Arc::new(Image::new(4, 2, pixels).unwrap()));
+
+        let at = |x, y, z| env.color(Vec3::new(x, y, z)).x();
+        assert_eq!(at(-1.0, 0.5, 1.0), 0.0);
+        assert_eq!(at(-1.0, 0.5, -1.0), 1.0);
+        assert_eq!(at(1.0, 0.5, -1.0), 2.0);
+        assert_eq!(at(1.0, 0.5, 1.0), 3.0);
+        assert_eq!(at(1.0, -0.5, -1.0), 6.0);
+    }
+
+    #[test]
+    fn test_uv_round_trip() {
+        for uv in [(0.5, 0.5), (0.1, 0.3), (0.8, 0.9)] {
+            let (u, v) = direction_to_uv(uv_to_direction(uv));
+            assert!((u - uv.0).abs() < 1e-12 && (v - uv.1).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_equirect_sampling_finds_the_sun() {
+        // A dim sky with one bright pixel just above the horizon.
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); 16 * 8];
+        pixels[3 * 16 + 5] = Color::new(1000.0, 1000.0, 1000.0);
+        let env = EquirectEnvironment::new(Arc::new(Image::new(16, 8, pixels).unwrap()));
+
+        let mut hits = 0;
+        for i in 0..100 {
+            let u = ((i % 10) as f64 + 0.5) / 10.0;
+            let v = ((i / 10) as f64 + 0.5) / 10.0;
+            let s = env.sample((u, v)).unwrap();
+            assert!((env.pdf(s.dir) - s.pdf).abs() < 1e-9 * s.pdf);
+            assert_eq!(s.radiance, env.color(s.dir));
+            if s.radiance.x() == 1000.0 {
+                hits += 1;
+            }
+        }
+        assert!(hits > 90);
+    }
+}
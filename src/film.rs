@@ -0,0 +1,282 @@
+use serde::Deserialize;
+
+use crate::filter::Filter;
+use crate::vec3::Color;
+
+// The linear radiance of every pixel, as the filter-weighted sum of the
+// samples around it and the sum of their weights. Sums are kept in single
+// precision and added to one sample number at a time, so the total does not
+// depend on how the samples were split into passes and tiles.
+//
+// The mean and variance of each sample's luminance are kept too, to tell how
+// far the pixel is from converging.
+pub struct Film {
+    width: u16,
+    height: u16,
+    filter: Filter,
+    sums: Vec<[f32; 3]>,
+    weights: Vec<f32>,
+    counts: Vec<u32>,
+    means: Vec<f64>,
+    squared_deviations: Vec<f64>,
+}
+
+// One sample of some of the pixels of the `w` by `h` rectangle whose top left
+// corner is pixel (x, y): where in the pixel it landed, measured from the
+// pixel's top left corner, and the radiance it carried.
+pub struct SampleGrid {
+    pub x: u16,
+    pub y: u16,
+    pub w: u16,
+    pub samples: Vec<Option<((f64, f64), Color)>>,
+}
+
+impl SampleGrid {
+    fn get(&self, col: u16, row: u16) -> ((f64, f64), Color) {
+        self.samples[(row - self.y) as usize * self.w as usize + (col - self.x) as usize]
+            .expect("the grid covers every pixel within the filter's reach")
+    }
+}
+
+// Keeps sampling a pixel only while the standard error of its luminance is
+// more than `threshold` of the luminance, or of 0.1 in darker pixels. Every
+// pixel takes at least `min_samples` samples, so the error can be estimated,
+// and at most `max_samples`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Adaptive {
+    pub threshold: f64,
+    pub min_samples: u32,
+    pub max_samples: u32,
+}
+
+impl Adaptive {
+    // The field that is out of range, and why.
+    pub fn check(&self) -> Result<(), (&'static str, &'static str)> {
+        if self.threshold <= 0.0 {
+            return Err(("threshold", "must be positive"));
+        }
+        if self.min_samples < 2 {
+            return Err(("min_samples", "must be at least 2"));
+        }
+        if self.max_samples < self.min_samples {
+            return Err(("max_samples", "must be at least min_samples"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            threshold: 0.02,
+            min_samples: 16,
+            max_samples: 1024,
+        }
+    }
+}
+
+impl Film {
+    pub fn new(width: u16, height: u16, filter: Filter) -> Self {
+        let len = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            filter,
+            sums: vec![[0.0; 3]; len],
+            weights: vec![0.0; len],
+            counts: vec![0; len],
+            means: vec![0.0; len],
+            squared_deviations: vec![0.0; len],
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    // How many pixels beyond a pixel its filter reaches.
+    pub fn margin(&self) -> u16 {
+        (self.filter.radius - 0.5).ceil().max(0.0) as u16
+    }
+
+    pub fn index(&self, col: u16, row: u16) -> usize {
+        row as usize * self.width as usize + col as usize
+    }
+
+    pub fn count(&self, index: usize) -> u32 {
+        self.counts[index]
+    }
+
+    // The filtered sum of the samples in `grid` around pixel (col, row), and
+    // the sum of their weights. The grid must cover every pixel within
+    // `margin` of it that is on the film.
+    pub fn gather(&self, col: u16, row: u16, grid: &SampleGrid) -> ([f32; 3], f32) {
+        let m = self.margin();
+        let (cols, rows) = (
+            col.saturating_sub(m)..=col.saturating_add(m).min(self.width - 1),
+            row.saturating_sub(m)..=row.saturating_add(m).min(self.height - 1),
+        );
+
+        let (mut sum, mut weight) = (Color::default(), 0.0);
+        for r in rows {
+            for c in cols.clone() {
+                let ((du, dv), color) = grid.get(c, r);
+                let dx = c as f64 + du - (col as f64 + 0.5);
+                let dy = r as f64 + dv - (row as f64 + 0.5);
+                let w = self.filter.weight(dx, dy);
+                if w != 0.0 {
+                    sum += w * color;
+                    weight += w;
+                }
+            }
+        }
+        (
+            [sum.x() as f32, sum.y() as f32, sum.z() as f32],
+            weight as f32,
+        )
+    }
+
+    // Adds the next sample of pixel `index`, as returned by `gather`.
+    pub fn add(&mut self, index: usize, (sum, weight): ([f32; 3], f32)) {
+        for (s, x) in self.sums[index].iter_mut().zip(sum) {
+            *s += x;
+        }
+        self.weights[index] += weight;
+        self.counts[index] += 1;
+
+        // Welford's running variance.
+        let [r, g, b] = sum;
+        let luminance = if weight == 0.0 {
+            0.0
+        } else {
+            Color::new(r as f64, g as f64, b as f64).luminance() / weight as f64
+        };
+        let n = self.counts[index] as f64;
+        let delta = luminance - self.means[index];
+        self.means[index] += delta / n;
+        self.squared_deviations[index] += delta * (luminance - self.means[index]);
+    }
+
+    // The standard error of pixel `index`'s mean luminance, relative to the
+    // luminance, or to 0.1 in darker pixels.
+    pub fn relative_error(&self, index: usize) -> f64 {
+        let n = self.counts[index] as f64;
+        if n < 2.0 {
+            return f64::INFINITY;
+        }
+        let variance = self.squared_deviations[index] / (n - 1.0);
+        (variance / n).sqrt() / self.means[index].max(0.1)
+    }
+
+    // How many more samples pixel `index` may take, up to `samples`.
+    pub fn wanted(&self, index: usize, samples: u32, adaptive: Option<&Adaptive>) -> u32 {
+        let Some(adaptive) = adaptive else {
+            return samples;
+        };
+        let count = self.counts[index];
+        if count >= adaptive.min_samples && self.relative_error(index) <= adaptive.threshold {
+            return 0;
+        }
+        samples.min(adaptive.max_samples.saturating_sub(count))
+    }
+
+    // The filtered average of pixel `index`'s samples, or black if it has none.
+    pub fn pixel(&self, index: usize) -> Color {
+        let [r, g, b] = self.sums[index];
+        let w = self.weights[index];
+        if w == 0.0 {
+            return Color::default();
+        }
+        Color::new(r as f64, g as f64, b as f64) / w as f64
+    }
+
+    // The fewest samples taken of any pixel.
+    pub fn samples(&self) -> u32 {
+        self.counts.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn most_samples(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn clear(&mut self) {
+        self.sums.fill([0.0; 3]);
+        self.weights.fill(0.0);
+        self.counts.fill(0);
+        self.means.fill(0.0);
+        self.squared_deviations.fill(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Adaptive, Film, SampleGrid};
+    use crate::filter::{Filter, FilterKind};
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_gather() {
+        // A white pixel between two black ones.
+        let grid = SampleGrid {
+            x: 0,
+            y: 0,
+            w: 3,
+            samples: [0.0, 1.0, 0.0]
+                .map(|c| Some(((0.5, 0.5), Color::new(c, c, c))))
+                .to_vec(),
+        };
+
+        let mut boxed = Film::new(3, 1, Filter::default());
+        assert_eq!(boxed.margin(), 0);
+        let sample = boxed.gather(1, 0, &grid);
+        assert_eq!(sample, ([1.0; 3], 1.0));
+        boxed.add(1, sample);
+        boxed.add(1, ([3.0, 3.0, 0.0], 1.0));
+        assert_eq!(boxed.pixel(1), Color::new(2.0, 2.0, 0.5));
+        assert_eq!(boxed.pixel(0), Color::default());
+
+        // A tent reaching past the neighbouring pixels' centres weighs their
+        // samples a third as much as the pixel's own.
+        let tent = Film::new(
+            3,
+            1,
+            Filter {
+                kind: FilterKind::Tent,
+                radius: 1.5,
+            },
+        );
+        assert_eq!(tent.margin(), 1);
+        assert_eq!(tent.gather(1, 0, &grid), ([2.25; 3], 3.75));
+        assert_eq!(tent.gather(0, 0, &grid), ([0.75; 3], 3.0));
+    }
+
+    #[test]
+    fn test_adaptive() {
+        let adaptive = Adaptive {
+            threshold: 0.1,
+            min_samples: 4,
+            max_samples: 6,
+        };
+        let mut film = Film::new(2, 1, Filter::default());
+        for i in 0..4 {
+            // A steady pixel and a flickering one.
+            film.add(0, ([0.5; 3], 1.0));
+            film.add(1, ([i as f32 % 2.0; 3], 1.0));
+        }
+        assert_eq!(film.relative_error(0), 0.0);
+        assert!((film.relative_error(1) - (1.0f64 / 12.0).sqrt() / 0.5).abs() < 1e-12);
+
+        assert_eq!(film.wanted(0, 8, Some(&adaptive)), 0);
+        assert_eq!(film.wanted(1, 8, Some(&adaptive)), 2);
+        assert_eq!(film.wanted(0, 8, None), 8);
+    }
+}
@@ -0,0 +1,139 @@
+use std::f64::consts::PI;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+// How much a sample counts towards the pixels around it, by its distance
+// from their centres.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    // Every sample within the radius counts the same.
+    Box,
+    // Falls off linearly to the radius.
+    Tent,
+    // A Gaussian with a standard deviation of a third of the radius, shifted
+    // down to reach zero at the radius.
+    Gaussian,
+    // Mitchell and Netravali's cubic with B = C = 1/3. Its negative lobes
+    // sharpen edges.
+    Mitchell,
+    // A sinc windowed by a sinc as wide as the radius, the sharpest of these.
+    Lanczos,
+}
+
+impl FilterKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "box" => Some(Self::Box),
+            "tent" => Some(Self::Tent),
+            "gaussian" => Some(Self::Gaussian),
+            "mitchell" => Some(Self::Mitchell),
+            "lanczos" => Some(Self::Lanczos),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Filter {
+    #[serde(rename = "type")]
+    pub kind: FilterKind,
+    // In pixels, so a box of radius 0.5 covers just the pixel itself.
+    pub radius: f64,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            kind: FilterKind::Box,
+            radius: 0.5,
+        }
+    }
+}
+
+impl Filter {
+    // The weight of a sample (dx, dy) pixels from a pixel's centre. The
+    // filters are separable, so this is the product of each axis' weight.
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, x: f64) -> f64 {
+        let (x, r) = (x.abs(), self.radius);
+        if x > r {
+            return 0.0;
+        }
+
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => r - x,
+            FilterKind::Gaussian => {
+                let g = |x: f64| (-4.5 * x * x / (r * r)).exp();
+                g(x) - g(r)
+            }
+            FilterKind::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = 2.0 * x / r;
+                let p = if x < 1.0 {
+                    (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b)
+                } else {
+                    (-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c)
+                };
+                p / 6.0
+            }
+            FilterKind::Lanczos => sinc(x) * sinc(x / r),
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, FilterKind};
+
+    fn filter(kind: FilterKind, radius: f64) -> Filter {
+        Filter { kind, radius }
+    }
+
+    #[test]
+    fn test_weights() {
+        let kinds = [
+            FilterKind::Box,
+            FilterKind::Tent,
+            FilterKind::Gaussian,
+            FilterKind::Mitchell,
+            FilterKind::Lanczos,
+        ];
+        for kind in kinds {
+            let f = filter(kind, 2.0);
+            // Largest at the centre, symmetric, and nothing past the radius.
+            assert!(f.weight(0.0, 0.0) > 0.0);
+            assert!(f.weight(0.0, 0.0) >= f.weight(0.7, 0.2));
+            assert_eq!(f.weight(0.7, -0.2), f.weight(-0.7, 0.2));
+            assert_eq!(f.weight(2.1, 0.0), 0.0);
+        }
+
+        assert_eq!(filter(FilterKind::Box, 0.5).weight(0.5, -0.5), 1.0);
+        assert_eq!(filter(FilterKind::Tent, 1.0).weight(0.5, 0.0), 0.5);
+        assert!(filter(FilterKind::Gaussian, 2.0).weight(2.0, 0.0).abs() < 1e-12);
+
+        // The sharpening filters dip below zero away from the centre.
+        assert!(filter(FilterKind::Mitchell, 2.0).weight(1.5, 0.0) < 0.0);
+        assert!(filter(FilterKind::Lanczos, 2.0).weight(1.5, 0.0) < 0.0);
+    }
+}
@@ -0,0 +1,579 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{Hittable, HittableList, Intersection};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::triangle::Triangle;
+use crate::vec3::{Point3, Vec3};
+
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+const MAX_DEPTH: usize = 64;
+// Cost of visiting an interior node relative to testing one primitive.
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BvhStats {
+    pub primitive_count: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub depth: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub mean_leaf_size: f64,
+    pub sah_cost: f64,
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    bbox: Aabb,
+    // A leaf's first primitive, or an interior node's second child. The first
+    // child of an interior node is always stored right after it.
+    offset: u32,
+    // Number of primitives in a leaf; zero for interior nodes.
+    count: u32,
+    axis: u8,
+}
+
+struct BuildItem {
+    bbox: Aabb,
+    centroid: Point3,
+    index: usize,
+}
+
+// A BVH over primitives identified only by their position, stored as a
+// depth-first array of nodes so traversal walks indices instead of pointers.
+pub struct BvhTree {
+    nodes: Vec<Node>,
+    stats: BvhStats,
+}
+
+impl BvhTree {
+    // Builds a tree over `bounds` with the binned surface area heuristic. The
+    // returned order lists the original index of each primitive slot; callers
+    // must store their primitives in that order before traversing.
+    pub fn build(bounds: &[Aabb]) -> (Self, Vec<usize>) {
+        let mut items: Vec<BuildItem> = bounds
+            .iter()
+            .enumerate()
+            .map(|(index, bbox)| BuildItem {
+                bbox: *bbox,
+                centroid: bbox.centroid(),
+                index,
+            })
+            .collect();
+
+        let mut tree = Self {
+            nodes: Vec::with_capacity(2 * items.len()),
+            stats: BvhStats::default(),
+        };
+        if !items.is_empty() {
+            tree.build_recursive(&mut items, 0, 1);
+        }
+        tree.stats = tree.compute_stats();
+
+        (tree, items.iter().map(|item| item.index).collect())
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::empty(), |n| n.bbox)
+    }
+
+    // Finds the closest hit among the primitives, asking `hit` to test the
+    // primitive in a given slot against the closest distance found so far.
+    pub fn traverse<'a, F>(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut hit: F,
+    ) -> Option<Intersection<'a>>
+    where
+        F: FnMut(usize, f64) -> Option<Intersection<'a>>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let (org, dir) = (r.origin(), r.direction());
+        let inv_dir = Vec3::new(1.0 / dir.x(), 1.0 / dir.y(), 1.0 / dir.z());
+
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut node = 0;
+
+        let mut result = None;
+        let mut t_closest = t_max;
+        loop {
+            let n = &self.nodes[node];
+            if n.bbox.hit_inv(org, inv_dir, t_min, t_closest) {
+                if n.count > 0 {
+                    for i in n.offset..n.offset + n.count {
+                        if let Some(rec) = hit(i as usize, t_closest) {
+                            t_closest = rec.t;
+                            result = Some(rec);
+                        }
+                    }
+                } else {
+                    // Visit the child on the near side of the split first so
+                    // the far one can be culled by a closer hit.
+                    let (near, far) = if dir[n.axis as usize] < 0.0 {
+                        (n.offset as usize, node + 1)
+                    } else {
+                        (node + 1, n.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    node = near;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            node = stack[stack_len];
+        }
+
+        result
+    }
+
+    // Whether any primitive lies along `r` between `t_min` and `t_max`,
+    // asking `hit` to test the primitive in a given slot. Stops at the first
+    // one found, in no particular order.
+    pub fn any<F>(&self, r: &Ray, t_min: f64, t_max: f64, mut hit: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let (org, dir) = (r.origin(), r.direction());
+        let inv_dir = Vec3::new(1.0 / dir.x(), 1.0 / dir.y(), 1.0 / dir.z());
+
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut node = 0;
+        loop {
+            let n = &self.nodes[node];
+            if n.bbox.hit_inv(org, inv_dir, t_min, t_max) {
+                if n.count > 0 {
+                    if (n.offset..n.offset + n.count).any(|i| hit(i as usize)) {
+                        return true;
+                    }
+                } else {
+                    stack[stack_len] = n.offset as usize;
+                    stack_len += 1;
+                    node += 1;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                return false;
+            }
+            stack_len -= 1;
+            node = stack[stack_len];
+        }
+    }
+
+    fn build_recursive(&mut self, items: &mut [BuildItem], offset: usize, depth: usize) {
+        let bbox = items
+            .iter()
+            .fold(Aabb::empty(), |acc, item| acc.surrounding(&item.bbox));
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            bbox,
+            offset: offset as u32,
+            count: items.len() as u32,
+            axis: 0,
+        });
+        self.stats.depth = self.stats.depth.max(depth);
+
+        let (axis, mid) = match Self::partition(items, &bbox, depth) {
+            Some(split) => split,
+            None => return,
+        };
+
+        let (left, right) = items.split_at_mut(mid);
+        self.build_recursive(left, offset, depth + 1);
+        let second = self.nodes.len();
+        self.build_recursive(right, offset + mid, depth + 1);
+
+        self.nodes[node] = Node {
+            bbox,
+            offset: second as u32,
+            count: 0,
+            axis: axis as u8,
+        };
+    }
+
+    // Reorders `items` around the cheapest binned SAH split and returns the
+    // split axis and position, or `None` if they should stay in one leaf.
+    fn partition(items: &mut [BuildItem], bbox: &Aabb, depth: usize) -> Option<(usize, usize)> {
+        let n = items.len();
+        if n == 1 || depth >= MAX_DEPTH {
+            return None;
+        }
+
+        let centroid_bounds = items.iter().fold(Aabb::empty(), |acc, item| {
+            acc.surrounding(&Aabb::new(item.centroid, item.centroid))
+        });
+        let axis = centroid_bounds.longest_axis();
+        let (lo, hi) = (centroid_bounds.min()[axis], centroid_bounds.max()[axis]);
+        if hi <= lo {
+            // Every centroid is in the same place, so no plane separates them.
+            return if n <= MAX_LEAF_SIZE {
+                None
+            } else {
+                Some((axis, n / 2))
+            };
+        }
+
+        let bin_of = |c: Point3| {
+            let b = ((c[axis] - lo) / (hi - lo) * BIN_COUNT as f64) as usize;
+            b.min(BIN_COUNT - 1)
+        };
+
+        let mut bins = [(Aabb::empty(), 0); BIN_COUNT];
+        for item in items.iter() {
+            let bin = &mut bins[bin_of(item.centroid)];
+            bin.0 = bin.0.surrounding(&item.bbox);
+            bin.1 += 1;
+        }
+
+        // Sweep from the right to collect what lies above each plane, then
+        // from the left to price every plane.
+        let mut above = [(0.0, 0); BIN_COUNT];
+        let (mut acc, mut count) = (Aabb::empty(), 0);
+        for i in (1..BIN_COUNT).rev() {
+            acc = acc.surrounding(&bins[i].0);
+            count += bins[i].1;
+            above[i] = (acc.surface_area(), count);
+        }
+
+        let (mut best_cost, mut best_bin) = (f64::INFINITY, 0);
+        let (mut acc, mut count) = (Aabb::empty(), 0);
+        for i in 0..BIN_COUNT - 1 {
+            acc = acc.surrounding(&bins[i].0);
+            count += bins[i].1;
+            let (area_above, count_above) = above[i + 1];
+            let cost = acc.surface_area() * count as f64 + area_above * count_above as f64;
+            if cost < best_cost {
+                best_cost = cost;
+                best_bin = i;
+            }
+        }
+
+        let area = bbox.surface_area();
+        let split_cost = TRAVERSAL_COST + if area > 0.0 { best_cost / area } else { 0.0 };
+        if n <= MAX_LEAF_SIZE && split_cost >= n as f64 {
+            return None;
+        }
+
+        let mut mid = 0;
+        for i in 0..n {
+            if bin_of(items[i].centroid) <= best_bin {
+                items.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        if mid == 0 || mid == n {
+            mid = n / 2;
+        }
+        Some((axis, mid))
+    }
+
+    fn compute_stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            node_count: self.nodes.len(),
+            depth: self.stats.depth,
+            min_leaf_size: usize::MAX,
+            ..Default::default()
+        };
+
+        let root_area = self.bounding_box().surface_area();
+        for node in &self.nodes {
+            let area = if root_area > 0.0 {
+                node.bbox.surface_area() / root_area
+            } else {
+                1.0
+            };
+
+            if node.count == 0 {
+                stats.sah_cost += TRAVERSAL_COST * area;
+                continue;
+            }
+
+            let size = node.count as usize;
+            stats.primitive_count += size;
+            stats.leaf_count += 1;
+            stats.min_leaf_size = stats.min_leaf_size.min(size);
+            stats.max_leaf_size = stats.max_leaf_size.max(size);
+            stats.sah_cost += size as f64 * area;
+        }
+
+        if stats.leaf_count == 0 {
+            stats.min_leaf_size = 0;
+        } else {
+            stats.mean_leaf_size = stats.primitive_count as f64 / stats.leaf_count as f64;
+        }
+
+        stats
+    }
+}
+
+// What a `FlatBvh` leaf holds. Spheres and triangles are stored by value, so
+// testing them takes neither a pointer chase nor a virtual call. Anything
+// else, such as a mesh with its own tree, is kept behind its box.
+enum Primitive {
+    Sphere(Sphere<Arc<dyn Material>>),
+    Triangle(Triangle<Arc<dyn Material>>),
+    Object(Box<dyn Hittable>),
+}
+
+impl Primitive {
+    fn new(obj: Box<dyn Hittable>) -> Self {
+        let obj = match downcast(obj) {
+            Ok(sphere) => return Self::Sphere(sphere),
+            Err(obj) => obj,
+        };
+        match downcast(obj) {
+            Ok(triangle) => Self::Triangle(triangle),
+            Err(obj) => Self::Object(obj),
+        }
+    }
+}
+
+fn downcast<T: Hittable>(obj: Box<dyn Hittable>) -> Result<T, Box<dyn Hittable>> {
+    if (&*obj as &dyn Any).is::<T>() {
+        let obj: Box<dyn Any> = obj;
+        Ok(*obj.downcast().expect("checked the type above"))
+    } else {
+        Err(obj)
+    }
+}
+
+impl Hittable for Primitive {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        match self {
+            Self::Sphere(s) => s.hit(r, t_min, t_max),
+            Self::Triangle(t) => t.hit(r, t_min, t_max),
+            Self::Object(o) => o.hit(r, t_min, t_max),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match self {
+            Self::Sphere(s) => s.bounding_box(),
+            Self::Triangle(t) => t.bounding_box(),
+            Self::Object(o) => o.bounding_box(),
+        }
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        match self {
+            Self::Sphere(s) => s.occluded(r, t_min, t_max),
+            Self::Triangle(t) => t.occluded(r, t_min, t_max),
+            Self::Object(o) => o.occluded(r, t_min, t_max),
+        }
+    }
+
+    fn sample(&self, origin: Point3, u: (f64, f64)) -> Option<Vec3> {
+        match self {
+            Self::Sphere(s) => s.sample(origin, u),
+            Self::Triangle(t) => t.sample(origin, u),
+            Self::Object(o) => o.sample(origin, u),
+        }
+    }
+
+    fn pdf(&self, origin: Point3, dir: Vec3) -> f64 {
+        match self {
+            Self::Sphere(s) => s.pdf(origin, dir),
+            Self::Triangle(t) => t.pdf(origin, dir),
+            Self::Object(o) => o.pdf(origin, dir),
+        }
+    }
+}
+
+pub struct FlatBvh {
+    tree: BvhTree,
+    objects: Vec<Primitive>,
+}
+
+impl FlatBvh {
+    pub fn new(list: HittableList) -> Self {
+        let objects: Vec<Primitive> = list
+            .into_objects()
+            .into_iter()
+            .map(Primitive::new)
+            .collect();
+        let bounds: Vec<Aabb> = objects.iter().map(|obj| obj.bounding_box()).collect();
+        let (tree, order) = BvhTree::build(&bounds);
+
+        let mut slots: Vec<_> = objects.into_iter().map(Some).collect();
+        let objects = order.iter().filter_map(|&i| slots[i].take()).collect();
+
+        Self { tree, objects }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.tree.stats()
+    }
+}
+
+impl Hittable for FlatBvh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        self.tree.traverse(r, t_min, t_max, |i, t_closest| {
+            self.objects[i].hit(r, t_min, t_closest)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.tree.bounding_box()
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.tree.any(r, t_min, t_max, |i| {
+            self.objects[i].occluded(r, t_min, t_max)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use std::sync::Arc;
+
+    use super::{FlatBvh, Primitive};
+    use crate::hittable::{Hittable, HittableList};
+    use crate::material::{Lambertian, Material};
+    use crate::mesh::TriangleMesh;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::triangle::Triangle;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn scene(n: usize) -> HittableList {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut world = HittableList::new();
+        for _ in 0..n {
+            let center = Point3::new(
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+            );
+            let radius = rng.gen_range(0.05..1.5);
+            let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+            world.add(Box::new(Sphere::new(center, radius, mat)));
+        }
+        world
+    }
+
+    // Spheres among triangles, loose and in a mesh.
+    fn mixed_scene() -> HittableList {
+        let mut world = scene(100);
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        for i in 0..20 {
+            let x = i as f64 * 2.0 - 20.0;
+            world.add(Box::new(Triangle::new(
+                Point3::new(x, -5.0, -5.0),
+                Point3::new(x + 1.5, -5.0, 5.0),
+                Point3::new(x, 5.0, 0.0),
+                mat.clone(),
+            )));
+        }
+        let quad = TriangleMesh::new(
+            vec![
+                Point3::new(-20.0, -20.0, 10.0),
+                Point3::new(20.0, -20.0, 10.0),
+                Point3::new(20.0, 20.0, 10.0),
+                Point3::new(-20.0, 20.0, 10.0),
+            ],
+            vec![],
+            vec![],
+            vec![[0, 1, 2], [0, 2, 3]],
+            mat,
+        );
+        world.add(Box::new(quad.unwrap()));
+        world
+    }
+
+    #[test]
+    fn test_inline_primitives() {
+        let bvh = FlatBvh::new(mixed_scene());
+        let count = |f: fn(&Primitive) -> bool| bvh.objects.iter().filter(|&p| f(p)).count();
+        assert_eq!(count(|p| matches!(p, Primitive::Sphere(_))), 100);
+        assert_eq!(count(|p| matches!(p, Primitive::Triangle(_))), 20);
+        assert_eq!(count(|p| matches!(p, Primitive::Object(_))), 1);
+    }
+
+    #[test]
+    fn test_hit_matches_list() {
+        for (list, bvh) in [
+            (scene(500), FlatBvh::new(scene(500))),
+            (mixed_scene(), FlatBvh::new(mixed_scene())),
+        ] {
+            check_hits(&list, &bvh);
+        }
+    }
+
+    fn check_hits(list: &HittableList, bvh: &FlatBvh) {
+        assert_eq!(bvh.bounding_box(), list.bounding_box());
+
+        let mut rng = SmallRng::seed_from_u64(2);
+        for _ in 0..2000 {
+            let r = Ray::new(
+                Point3::new(
+                    rng.gen_range(-25.0..25.0),
+                    rng.gen_range(-25.0..25.0),
+                    rng.gen_range(-25.0..25.0),
+                ),
+                Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                ),
+            );
+
+            let (want, got) = (
+                list.hit(&r, 0.001, f64::INFINITY),
+                bvh.hit(&r, 0.001, f64::INFINITY),
+            );
+            assert_eq!(want.map(|i| (i.t, i.p)), got.map(|i| (i.t, i.p)));
+
+            // Shadow rays agree with the closest hit, short of it and past it.
+            let t = want.map_or(f64::INFINITY, |i| i.t);
+            assert_eq!(bvh.occluded(&r, 0.001, f64::INFINITY), want.is_some());
+            assert!(!bvh.occluded(&r, 0.001, t * 0.999));
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = FlatBvh::new(scene(500)).stats();
+        assert_eq!(stats.primitive_count, 500);
+        assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+        assert!(stats.min_leaf_size >= 1 && stats.max_leaf_size <= 4);
+        assert!(stats.depth > 1 && stats.depth <= 64);
+        // Testing every sphere costs 500; the tree must be far cheaper.
+        assert!(stats.sah_cost > 0.0 && stats.sah_cost < 50.0, "{stats:?}");
+
+        let empty = FlatBvh::new(HittableList::new());
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(empty.hit(&r, 0.001, f64::INFINITY).is_none());
+        assert_eq!(empty.stats().node_count, 0);
+    }
+}
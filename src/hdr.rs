@@ -0,0 +1,281 @@
+use std::fmt;
+
+use crate::image::Image;
+use crate::vec3::Color;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrError(String);
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid Radiance HDR file: {}", self.0)
+    }
+}
+
+impl std::error::Error for HdrError {}
+
+// The most pixels decoded, as in an 8192 by 4096 environment map, so that a
+// header can't ask for more memory than there is.
+const MAX_PIXELS: usize = 8192 * 4096;
+
+fn error<T>(message: impl Into<String>) -> Result<T, HdrError> {
+    Err(HdrError(message.into()))
+}
+
+// Decodes a Radiance RGBE (.hdr) image with either flat or run-length
+// encoded scanlines.
+pub fn decode_hdr(bytes: &[u8]) -> Result<Image, HdrError> {
+    let mut r = Reader { bytes, pos: 0 };
+
+    let magic = r.line()?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return error("missing #?RADIANCE signature");
+    }
+
+    loop {
+        let line = r.line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return error(format!("unsupported format {format}"));
+            }
+        }
+    }
+
+    let resolution = r.line()?;
+    let (flip_y, height, width): (bool, usize, usize) =
+        match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            [y @ ("-Y" | "+Y"), h, "+X", w] => match (h.parse(), w.parse()) {
+                (Ok(h), Ok(w)) if h > 0 && w > 0 => (y == "+Y", h, w),
+                _ => return error(format!("invalid resolution \"{resolution}\"")),
+            },
+            _ => return error(format!("unsupported orientation \"{resolution}\"")),
+        };
+    if width.checked_mul(height).is_none_or(|n| n > MAX_PIXELS) {
+        return error(format!("{width}x{height} is too large"));
+    }
+    // Every scanline takes at least one pixel's four bytes.
+    if height > (bytes.len() - r.pos) / 4 {
+        return error("unexpected end of file");
+    }
+
+    let mut rows = Vec::with_capacity(height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        r.scanline(&mut scanline)?;
+        rows.push(
+            scanline
+                .iter()
+                .map(|&rgbe| rgbe_to_color(rgbe))
+                .collect::<Vec<_>>(),
+        );
+    }
+    if flip_y {
+        rows.reverse();
+    }
+
+    Image::new(width, height, rows.concat()).map_err(|e| HdrError(e.to_string()))
+}
+
+fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::default();
+    }
+    let f = 2f64.powi(e as i32 - (128 + 8));
+    Color::new(
+        (r as f64 + 0.5) * f,
+        (g as f64 + 0.5) * f,
+        (b as f64 + 0.5) * f,
+    )
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, HdrError> {
+        match self.bytes.get(self.pos) {
+            Some(&b) => {
+                self.pos += 1;
+                Ok(b)
+            }
+            None => error("unexpected end of file"),
+        }
+    }
+
+    fn rgbe(&mut self) -> Result<[u8; 4], HdrError> {
+        Ok([self.byte()?, self.byte()?, self.byte()?, self.byte()?])
+    }
+
+    fn line(&mut self) -> Result<String, HdrError> {
+        let rest = &self.bytes[self.pos..];
+        match rest.iter().position(|&b| b == b'\n') {
+            Some(end) => {
+                self.pos += end + 1;
+                Ok(String::from_utf8_lossy(&rest[..end]).trim_end().to_string())
+            }
+            None => error("unexpected end of header"),
+        }
+    }
+
+    fn scanline(&mut self, out: &mut [[u8; 4]]) -> Result<(), HdrError> {
+        let width = out.len();
+        let first = self.rgbe()?;
+
+        // Run-length encoded scanlines start with 2, 2 and the width, and
+        // store each channel separately.
+        if !(8..0x8000).contains(&width) || first[0] != 2 || first[1] != 2 || first[2] & 0x80 != 0 {
+            return self.flat_scanline(first, out);
+        }
+        if ((first[2] as usize) << 8 | first[3] as usize) != width {
+            return error("scanline width mismatch");
+        }
+
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = self.byte()? as usize;
+                if count > 128 {
+                    let count = count - 128;
+                    if x + count > width {
+                        return error("run overflows scanline");
+                    }
+                    let value = self.byte()?;
+                    for px in &mut out[x..x + count] {
+                        px[channel] = value;
+                    }
+                    x += count;
+                } else {
+                    if count == 0 || x + count > width {
+                        return error("bad literal run in scanline");
+                    }
+                    for px in &mut out[x..x + count] {
+                        px[channel] = self.byte()?;
+                    }
+                    x += count;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Uncompressed pixels, possibly with the original Radiance encoding where
+    // a (1, 1, 1, n) pixel repeats the previous one.
+    fn flat_scanline(&mut self, first: [u8; 4], out: &mut [[u8; 4]]) -> Result<(), HdrError> {
+        let mut x = 0;
+        let mut shift = 0;
+        let mut pixel = first;
+        loop {
+            if pixel[..3] == [1, 1, 1] && x > 0 {
+                if pixel[3] == 0 {
+                    return error("empty run in scanline");
+                }
+                if shift > usize::BITS - 8 {
+                    return error("run overflows scanline");
+                }
+                let count = (pixel[3] as usize) << shift;
+                if count > out.len() - x {
+                    return error("run overflows scanline");
+                }
+                let prev = out[x - 1];
+                out[x..x + count].fill(prev);
+                x += count;
+                shift += 8;
+            } else {
+                out[x] = pixel;
+                x += 1;
+                shift = 0;
+            }
+
+            if x == out.len() {
+                return Ok(());
+            }
+            pixel = self.rgbe()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_hdr;
+    use crate::vec3::Color;
+
+    fn header(resolution: &str) -> Vec<u8> {
+        format!("#?RADIANCE\n# made by hand\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n").into_bytes()
+    }
+
+    #[test]
+    fn test_flat() {
+        let mut bytes = header("-Y 2 +X 2");
+        // The second pixel repeats the first in the old run-length encoding.
+        bytes.extend([128, 64, 0, 129, 1, 1, 1, 1, 0, 0, 0, 0, 10, 20, 30, 128]);
+        let img = decode_hdr(&bytes).unwrap();
+
+        assert_eq!((img.width(), img.height()), (2, 2));
+        assert_eq!(
+            img.pixel(0, 0),
+            Color::new(1.00390625, 0.50390625, 0.00390625)
+        );
+        assert_eq!(img.pixel(1, 0), img.pixel(0, 0));
+        assert_eq!(img.pixel(0, 1), Color::default());
+        assert_eq!(img.pixel(1, 1).x(), 10.5 / 256.0);
+    }
+
+    #[test]
+    fn test_rle() {
+        let mut bytes = header("+Y 2 +X 8");
+        for row in [1u8, 2] {
+            bytes.extend([2, 2, 0, 8]);
+            // Red: a run of 8; green: 8 literals; blue: two runs of 4; exponent: a run of 8.
+            bytes.extend([128 + 8, 64 * row]);
+            bytes.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]);
+            bytes.extend([128 + 4, 0, 128 + 4, 255]);
+            bytes.extend([128 + 8, 136]);
+        }
+        let img = decode_hdr(&bytes).unwrap();
+
+        // +Y stores the bottom row first.
+        assert_eq!(img.pixel(0, 0), Color::new(128.5, 0.5, 0.5));
+        assert_eq!(img.pixel(7, 1), Color::new(64.5, 7.5, 255.5));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(decode_hdr(b"P6\n").is_err());
+        assert!(decode_hdr(&header("-Y 2 -X 2")).is_err());
+
+        let mut truncated = header("-Y 1 +X 8");
+        truncated.extend([2, 2, 0, 8, 128 + 8]);
+        assert_eq!(
+            decode_hdr(&truncated).err().unwrap().to_string(),
+            "invalid Radiance HDR file: unexpected end of file"
+        );
+
+        // Empty runs would otherwise shift the next run's count ever further.
+        let mut empty = header("-Y 1 +X 4");
+        empty.extend([5, 5, 5, 128]);
+        empty.extend([1, 1, 1, 0].repeat(10));
+        assert_eq!(
+            decode_hdr(&empty).err().unwrap().to_string(),
+            "invalid Radiance HDR file: empty run in scanline"
+        );
+        let mut long = header("-Y 1 +X 4");
+        long.extend([5, 5, 5, 128, 1, 1, 1, 1, 1, 1, 1, 1]);
+        assert!(decode_hdr(&long).is_err());
+
+        // Sizes are checked before anything is allocated for them.
+        assert_eq!(
+            decode_hdr(&header("-Y 1 +X 4000000000"))
+                .err()
+                .unwrap()
+                .to_string(),
+            "invalid Radiance HDR file: 4000000000x1 is too large"
+        );
+        assert!(decode_hdr(&header("-Y 4000 +X 4000")).is_err());
+    }
+}
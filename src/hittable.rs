@@ -1,50 +1,125 @@
-use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::any::Any;
+use std::sync::Arc;
 
-use crate::material::{Dielectric, Lambertian, Material, Metal};
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::light::LightList;
+use crate::material::{Dielectric, Lambertian, Material, Metal, Numbered};
 use crate::ray::Ray;
 use crate::sphere::Sphere;
 use crate::vec3::{Color, Point3, Vec3};
 
+#[derive(Clone, Copy)]
 pub struct Intersection<'a> {
     pub p: Point3,
     pub normal: Vec3,
     pub mat: &'a dyn Material,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
 }
 
 impl<'a> Intersection<'a> {
-    pub fn new(p: Point3, normal: Vec3, mat: &'a dyn Material, t: f64, front_face: bool) -> Self {
+    pub fn new(
+        p: Point3,
+        normal: Vec3,
+        mat: &'a dyn Material,
+        t: f64,
+        (u, v): (f64, f64),
+        front_face: bool,
+    ) -> Self {
         Self {
             p,
             normal,
             mat,
             t,
+            u,
+            v,
             front_face,
         }
     }
 }
 
-pub trait Hittable {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection>;
+// Shared by every thread when rendering in parallel. `Any` lets a `FlatBvh`
+// pick out the shapes it stores inline.
+pub trait Hittable: Any + Send + Sync {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>>;
+    fn bounding_box(&self) -> Aabb;
+
+    // Whether anything lies along `r` between `t_min` and `t_max`. Shadow rays
+    // only need to know that, so this can stop at the first hit found.
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit(r, t_min, t_max).is_some()
+    }
+
+    // Objects that can be sampled as lights pick a unit direction from
+    // `origin` towards a point on their surface, chosen by `u`.
+    fn sample(&self, _origin: Point3, _u: (f64, f64)) -> Option<Vec3> {
+        None
+    }
+
+    // The density per solid angle `sample` picks `dir` with, or zero if `dir`
+    // misses the object.
+    fn pdf(&self, _origin: Point3, _dir: Vec3) -> f64 {
+        0.0
+    }
+}
+
+// The random scene's materials, all of one type so that its spheres are too.
+fn shared(mat: impl Material + 'static) -> Arc<dyn Material> {
+    Arc::new(mat)
+}
+
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        (**self).occluded(r, t_min, t_max)
+    }
+
+    fn sample(&self, origin: Point3, u: (f64, f64)) -> Option<Vec3> {
+        (**self).sample(origin, u)
+    }
+
+    fn pdf(&self, origin: Point3, dir: Vec3) -> f64 {
+        (**self).pdf(origin, dir)
+    }
 }
 
-pub struct HittableList(Vec<Box<dyn Hittable>>);
+// The objects of a scene, and which of them are lights.
+#[derive(Default)]
+pub struct HittableList {
+    objects: Vec<Box<dyn Hittable>>,
+    lights: LightList,
+}
 
 impl HittableList {
     pub fn new() -> Self {
-        Self(vec![])
+        Self::default()
     }
 
-    pub fn random_scene() -> Result<Self, rand::Error> {
-        let mut rng = SmallRng::from_rng(rand::thread_rng())?;
-
+    // The cover of "Ray Tracing in One Weekend". The same `rng` state always
+    // builds the same scene.
+    pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        // Every sphere has a material of its own, numbered in the order
+        // they are added.
         let mut world = Self::new();
 
         world.add(Box::new(Sphere::new(
             Point3::new(0.0, -1000.0, 0.0),
             1000.0,
-            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+            shared(Numbered::new(
+                world.objects.len(),
+                Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+            )),
         )));
 
         for a in -11..11 {
@@ -60,18 +135,30 @@ impl HittableList {
                 }
 
                 match rng.gen_range(0.0..1.0) {
-                    f if 0.0 <= f && f < 0.8 => {
+                    f if (0.0..0.8).contains(&f) => {
                         // diffuse
-                        let albedo = Color::random(0., 1.) * Color::random(0., 1.);
-                        world.add(Box::new(Sphere::new(center, 0.2, Lambertian::new(albedo))));
+                        let albedo = Color::random(rng, 0., 1.) * Color::random(rng, 0., 1.);
+                        world.add(Box::new(Sphere::new(
+                            center,
+                            0.2,
+                            shared(Numbered::new(world.objects.len(), Lambertian::new(albedo))),
+                        )));
                     }
-                    f if 0.8 <= f && f < 0.95 => {
+                    f if (0.8..0.95).contains(&f) => {
                         // metal
-                        let albedo = Color::random(0.5, 1.);
+                        let albedo = Color::random(rng, 0.5, 1.);
                         let fuzz = rng.gen_range(0.0..0.5);
-                        world.add(Box::new(Sphere::new(center, 0.2, Metal::new(albedo, fuzz))));
+                        world.add(Box::new(Sphere::new(
+                            center,
+                            0.2,
+                            shared(Numbered::new(world.objects.len(), Metal::new(albedo, fuzz))),
+                        )));
                     }
-                    _ => world.add(Box::new(Sphere::new(center, 0.2, Dielectric::new(1.5)))), // glass
+                    _ => world.add(Box::new(Sphere::new(
+                        center,
+                        0.2,
+                        shared(Numbered::new(world.objects.len(), Dielectric::new(1.5))),
+                    ))), // glass
                 };
             }
         }
@@ -79,32 +166,52 @@ impl HittableList {
         world.add(Box::new(Sphere::new(
             Point3::new(0., 1., 0.),
             1.,
-            Dielectric::new(1.5),
+            shared(Numbered::new(world.objects.len(), Dielectric::new(1.5))),
         )));
         world.add(Box::new(Sphere::new(
             Point3::new(-4., 1., 0.),
             1.,
-            Lambertian::new(Color::new(0.4, 0.2, 0.1)),
+            shared(Numbered::new(
+                world.objects.len(),
+                Lambertian::new(Color::new(0.4, 0.2, 0.1)),
+            )),
         )));
         world.add(Box::new(Sphere::new(
             Point3::new(4., 1., 0.),
             1.,
-            Metal::new(Color::new(0.7, 0.6, 0.5), 0.0),
+            shared(Numbered::new(
+                world.objects.len(),
+                Metal::new(Color::new(0.7, 0.6, 0.5), 0.0),
+            )),
         )));
 
-        Ok(world)
+        world
     }
 
     pub fn add(&mut self, h: Box<dyn Hittable>) {
-        self.0.push(h)
+        self.objects.push(h)
+    }
+
+    // Adds an object that is also sampled as a light.
+    pub fn add_light(&mut self, light: Arc<dyn Hittable>) {
+        self.lights.add(light.clone());
+        self.objects.push(Box::new(light))
+    }
+
+    pub fn lights(&self) -> &LightList {
+        &self.lights
+    }
+
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
     }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
         let mut result = None;
         let mut t_closest = t_max;
-        for obj in &self.0 {
+        for obj in &self.objects {
             if let Some(rec) = obj.hit(r, t_min, t_closest) {
                 t_closest = rec.t;
                 result = Some(rec);
@@ -113,4 +220,14 @@ impl Hittable for HittableList {
 
         result
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::empty(), |acc, obj| {
+            acc.surrounding(&obj.bounding_box())
+        })
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.objects.iter().any(|obj| obj.occluded(r, t_min, t_max))
+    }
 }
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use crate::vec3::Color;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageError(String);
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid image: {}", self.0)
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+// A linear-light RGB image, stored row by row from the top.
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    // Nothing can sample an image without pixels, so those are refused along
+    // with ones whose pixels don't fill their rows and columns.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Result<Self, ImageError> {
+        if width == 0 || height == 0 {
+            return Err(ImageError(format!(
+                "a {width}x{height} image has no pixels"
+            )));
+        }
+        if pixels.len() != width * height {
+            return Err(ImageError(format!(
+                "{} pixels don't fill a {width}x{height} image",
+                pixels.len()
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Image, ImageError};
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_new() {
+        let image = Image::new(2, 1, vec![Color::new(1.0, 0.0, 0.0); 2]).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+
+        let err = |width, height, len| Image::new(width, height, vec![Color::default(); len]).err();
+        let want = |message: &str| Some(ImageError(message.to_string()));
+        assert_eq!(err(0, 0, 0), want("a 0x0 image has no pixels"));
+        assert_eq!(err(4, 0, 0), want("a 4x0 image has no pixels"));
+        assert_eq!(err(2, 2, 3), want("3 pixels don't fill a 2x2 image"));
+    }
+}
@@ -0,0 +1,798 @@
+use wasm_bindgen::prelude::*;
+
+use crate::environment::Environment;
+use crate::hittable::{Hittable, Intersection};
+use crate::light::LightList;
+use crate::material::Scatter;
+use crate::pdf::{CosinePdf, EnvironmentPdf, HittablePdf, MixturePdf, Pdf};
+use crate::ray::Ray;
+use crate::sampler::{mix, Sampler};
+use crate::vec3::{Color, Point3, Vec3};
+
+// Turns a camera ray into the radiance it brings back. Shared by every
+// thread when rendering in parallel.
+pub trait Integrator: Send + Sync {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &LightList,
+        env: &dyn Environment,
+        sampler: &mut dyn Sampler,
+    ) -> Color;
+}
+
+// Which `Integrator` a render uses: the full path tracer, or a quicker view
+// for checking a scene's geometry and materials.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntegratorKind {
+    Path,
+    Whitted,
+    AmbientOcclusion,
+    Normals,
+    Depth,
+    Uv,
+    MaterialId,
+    Albedo,
+}
+
+impl IntegratorKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Self::Path),
+            "whitted" => Some(Self::Whitted),
+            "ambient_occlusion" => Some(Self::AmbientOcclusion),
+            "normals" => Some(Self::Normals),
+            "depth" => Some(Self::Depth),
+            "uv" => Some(Self::Uv),
+            "material_id" => Some(Self::MaterialId),
+            "albedo" => Some(Self::Albedo),
+            _ => None,
+        }
+    }
+}
+
+// Follows each path for up to `max_depth` surfaces, sampling the lights
+// from every diffuse one. Once a path has bounced `min_bounces` times, each
+// further bounce goes on with a probability that falls with the path's
+// throughput, and what it carries is scaled up to make up for the paths cut
+// short.
+pub struct PathIntegrator {
+    max_depth: u16,
+    min_bounces: u16,
+}
+
+impl PathIntegrator {
+    pub fn new(max_depth: u16, min_bounces: u16) -> Self {
+        Self {
+            max_depth,
+            min_bounces,
+        }
+    }
+
+    // Light arriving straight from the lights or the environment, along a
+    // direction picked towards them and weighted against the surface's own
+    // `scatter` strategy.
+    fn sample_direct(
+        r_in: &Ray,
+        world: &dyn Hittable,
+        lights: &LightList,
+        env: &dyn Environment,
+        i: &Intersection,
+        scatter: &dyn Pdf,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let towards = LightPdf::new(lights, env, i.p);
+        let dir = match towards.generate(sampler) {
+            Some(dir) => dir,
+            None => return Color::default(),
+        };
+        let scatter_pdf = scatter.value(dir);
+        if scatter_pdf <= 0.0 {
+            return Color::default();
+        }
+        let radiance = match arriving(world, lights, env, i.p, dir) {
+            Some(radiance) => radiance,
+            None => return Color::default(),
+        };
+
+        let light_pdf = towards.value(dir);
+        if light_pdf <= 0.0 {
+            return Color::default();
+        }
+        power_heuristic(light_pdf, scatter_pdf) / light_pdf * i.mat.eval(r_in, i, dir) * radiance
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &LightList,
+        env: &dyn Environment,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut ray = *ray;
+        let mut radiance = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        // The density a diffuse bounce chose `ray` with. Light that direct
+        // lighting could also have reached is weighted against that sample,
+        // so neither strategy counts it twice.
+        let mut scatter_pdf = None;
+
+        for depth in 0..self.max_depth {
+            let weight = |ray: &Ray, scatter_pdf: Option<f64>| match scatter_pdf {
+                Some(pdf) => {
+                    let light_pdf = LightPdf::new(lights, env, ray.origin()).value(ray.direction());
+                    power_heuristic(pdf, light_pdf)
+                }
+                None => 1.0,
+            };
+
+            let i = match world.hit(&ray, 0.001, f64::INFINITY) {
+                Some(i) => i,
+                None => {
+                    let background = env.color(ray.direction());
+                    radiance += weight(&ray, scatter_pdf) * throughput * background;
+                    break;
+                }
+            };
+
+            if i.mat.is_emissive() {
+                let emitted = i.mat.emitted(i.u, i.v, i.p);
+                radiance += weight(&ray, scatter_pdf) * throughput * emitted;
+            }
+
+            match i.mat.scatter(&ray, &i, sampler) {
+                Some(Scatter::Specular {
+                    attenuation,
+                    ray: scattered,
+                }) => {
+                    throughput = throughput * attenuation;
+                    scatter_pdf = None;
+                    ray = scattered;
+                }
+                Some(Scatter::Diffuse { pdf }) => {
+                    let direct = Self::sample_direct(&ray, world, lights, env, &i, &pdf, sampler);
+                    radiance += throughput * direct;
+
+                    let dir = match pdf.generate(sampler) {
+                        Some(dir) => dir,
+                        None => break,
+                    };
+                    let p = pdf.value(dir);
+                    if p <= 0.0 {
+                        break;
+                    }
+                    throughput = throughput * i.mat.eval(&ray, &i, dir) / p;
+                    scatter_pdf = Some(p);
+                    ray = Ray::new(i.p, dir);
+                }
+                None => break,
+            }
+
+            // Russian roulette. The last surface never bounces, so it takes
+            // no sample for it.
+            if depth >= self.min_bounces && depth + 1 < self.max_depth {
+                let survival = throughput.max_component().min(0.95);
+                if sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+
+        radiance
+    }
+}
+
+// Follows mirrors and glass for up to `max_depth` surfaces, and stops at the
+// first diffuse one, lighting it with a single shadow ray. Glass sends a ray
+// both ways, each carrying its Fresnel share of the light, so it comes out
+// without noise. Light bouncing between diffuse surfaces is missed, so
+// images are darker in the shade but converge quickly.
+pub struct WhittedIntegrator {
+    max_depth: u16,
+}
+
+impl WhittedIntegrator {
+    // Rays carrying less than this much of the light at the camera are not
+    // followed, which keeps glass from branching exponentially.
+    const CUTOFF: f64 = 1e-3;
+
+    pub fn new(max_depth: u16) -> Self {
+        Self { max_depth }
+    }
+
+    // The light arriving along `ray`, which carries `weight` of it to the
+    // camera, followed for up to `depth` more surfaces.
+    fn trace(
+        &self,
+        ray: &Ray,
+        weight: Color,
+        depth: u16,
+        scene: &SceneRef,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let SceneRef { world, lights, env } = *scene;
+        if depth == 0 || weight.max_component() < Self::CUTOFF {
+            return Color::default();
+        }
+        let i = match world.hit(ray, 0.001, f64::INFINITY) {
+            Some(i) => i,
+            None => return env.color(ray.direction()),
+        };
+        let mut radiance = Color::default();
+        if i.mat.is_emissive() {
+            radiance += i.mat.emitted(i.u, i.v, i.p);
+        }
+
+        let follow = |share: Color, r: &Ray, sampler: &mut dyn Sampler| {
+            share * self.trace(r, weight * share, depth - 1, scene, sampler)
+        };
+        if let Some(split) = i.mat.split(ray, &i) {
+            let (share, reflected) = split.reflected;
+            radiance += follow(share, &reflected, sampler);
+            if let Some((share, refracted)) = split.refracted {
+                radiance += follow(share, &refracted, sampler);
+            }
+            return radiance;
+        }
+
+        match i.mat.scatter(ray, &i, sampler) {
+            Some(Scatter::Specular {
+                attenuation,
+                ray: scattered,
+            }) => radiance += follow(attenuation, &scattered, sampler),
+            Some(Scatter::Diffuse { .. }) => {
+                let towards = LightPdf::new(lights, env, i.p);
+                radiance += match towards.generate(sampler) {
+                    Some(dir) => {
+                        let pdf = towards.value(dir);
+                        match arriving(world, lights, env, i.p, dir) {
+                            Some(arriving) if pdf > 0.0 => {
+                                i.mat.eval(ray, &i, dir) * arriving / pdf
+                            }
+                            _ => Color::default(),
+                        }
+                    }
+                    // With nothing to aim at, the sky straight above the
+                    // surface stands in for all of it.
+                    None if !world.occluded(&Ray::new(i.p, i.normal), 0.001, f64::INFINITY) => {
+                        i.mat.albedo(&i) * env.color(i.normal)
+                    }
+                    None => Color::default(),
+                };
+            }
+            None => {}
+        }
+        radiance
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &LightList,
+        env: &dyn Environment,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let weight = Color::new(1.0, 1.0, 1.0);
+        let scene = SceneRef { world, lights, env };
+        self.trace(ray, weight, self.max_depth, &scene, sampler)
+    }
+}
+
+// What `WhittedIntegrator::trace` passes down to each branch it follows.
+#[derive(Clone, Copy)]
+struct SceneRef<'a> {
+    world: &'a dyn Hittable,
+    lights: &'a LightList,
+    env: &'a dyn Environment,
+}
+
+// White where a surface is open to the sky within `distance` of it, fading to
+// black in creases and corners. Lights and materials are ignored.
+pub struct AmbientOcclusionIntegrator {
+    distance: f64,
+}
+
+impl AmbientOcclusionIntegrator {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _: &LightList,
+        _: &dyn Environment,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let i = match world.hit(ray, 0.001, f64::INFINITY) {
+            Some(i) => i,
+            None => return Color::new(1.0, 1.0, 1.0),
+        };
+        // Cosine weighted, as diffuse light would arrive.
+        match CosinePdf::new(i.normal).generate(sampler) {
+            Some(dir) if !world.occluded(&Ray::new(i.p, dir), 0.001, self.distance) => {
+                Color::new(1.0, 1.0, 1.0)
+            }
+            _ => Color::default(),
+        }
+    }
+}
+
+// What a `DebugIntegrator` shows of the first surface each ray hits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugView {
+    // The outward normal, with each axis mapped from -1..1 to 0..1.
+    Normals,
+    // The distance from the camera, unscaled, so that EXR output keeps it.
+    // Exposure brings far surfaces into view.
+    Depth,
+    // Texture coordinates as red and green.
+    Uv,
+    // A colour for each material.
+    MaterialId,
+    // The colour each material tints light with.
+    Albedo,
+}
+
+// Shows a property of the first surface hit, and black where rays miss.
+pub struct DebugIntegrator {
+    view: DebugView,
+}
+
+impl DebugIntegrator {
+    pub fn new(view: DebugView) -> Self {
+        Self { view }
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _: &LightList,
+        _: &dyn Environment,
+        _: &mut dyn Sampler,
+    ) -> Color {
+        let i = match world.hit(ray, 0.001, f64::INFINITY) {
+            Some(i) => i,
+            None => return Color::default(),
+        };
+        match self.view {
+            DebugView::Normals => {
+                let outward = if i.front_face { i.normal } else { -i.normal };
+                0.5 * (outward + Vec3::new(1.0, 1.0, 1.0))
+            }
+            DebugView::Depth => {
+                let depth = i.t * ray.direction().length();
+                Color::new(depth, depth, depth)
+            }
+            DebugView::Uv => Color::new(i.u, i.v, 0.0),
+            DebugView::MaterialId => {
+                // Bright enough to tell apart even at the dark end.
+                let hash = mix(i.mat.id() as u64);
+                let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+                Color::new(channel(0), channel(8), channel(16))
+            }
+            DebugView::Albedo => i.mat.albedo(&i),
+        }
+    }
+}
+
+// The light arriving at `p` from `dir`: from whatever light is seen first
+// that way, which may be another than the one aimed at, or else from the
+// environment. `None` if something else is in the way.
+fn arriving(
+    world: &dyn Hittable,
+    lights: &LightList,
+    env: &dyn Environment,
+    p: Point3,
+    dir: Vec3,
+) -> Option<Color> {
+    let shadow = Ray::new(p, dir);
+    match lights.hit(&shadow, 0.001, f64::INFINITY) {
+        Some(l) if !world.occluded(&shadow, 0.001, l.t - 0.001) => {
+            Some(l.mat.emitted(l.u, l.v, l.p))
+        }
+        None if !world.occluded(&shadow, 0.001, f64::INFINITY) => Some(env.color(dir)),
+        _ => None,
+    }
+}
+
+// Aims shadow rays from `origin` at the lights and at the environment when it
+// can be sampled, half and half when there are both. Scenes with neither
+// take no samples here.
+struct LightPdf<'a> {
+    env: EnvironmentPdf<'a>,
+    lights: HittablePdf<'a>,
+    sample_env: bool,
+    sample_lights: bool,
+}
+
+impl<'a> LightPdf<'a> {
+    fn new(lights: &'a LightList, env: &'a dyn Environment, origin: Point3) -> Self {
+        Self {
+            env: EnvironmentPdf::new(env),
+            lights: HittablePdf::new(lights, origin),
+            sample_env: env.is_sampled(),
+            sample_lights: !lights.is_empty(),
+        }
+    }
+
+    fn both(&self) -> MixturePdf<'_> {
+        MixturePdf::new(&self.env, &self.lights, 0.5)
+    }
+}
+
+impl Pdf for LightPdf<'_> {
+    fn value(&self, dir: Vec3) -> f64 {
+        match (self.sample_env, self.sample_lights) {
+            (true, true) => self.both().value(dir),
+            (true, false) => self.env.value(dir),
+            (false, true) => self.lights.value(dir),
+            (false, false) => 0.0,
+        }
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        match (self.sample_env, self.sample_lights) {
+            (true, true) => self.both().generate(sampler),
+            (true, false) => self.env.generate(sampler),
+            (false, true) => self.lights.generate(sampler),
+            (false, false) => None,
+        }
+    }
+}
+
+// Veach's power heuristic weight for a sample drawn with density `f` when
+// `g` could also have produced it.
+fn power_heuristic(f: f64, g: f64) -> f64 {
+    let (f2, g2) = (f * f, g * g);
+    if f2 + g2 == 0.0 {
+        0.0
+    } else {
+        f2 / (f2 + g2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        AmbientOcclusionIntegrator, DebugIntegrator, DebugView, Integrator, PathIntegrator,
+        WhittedIntegrator,
+    };
+    use crate::environment::{EquirectEnvironment, SolidEnvironment};
+    use crate::hittable::{Hittable, HittableList};
+    use crate::image::Image;
+    use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, Numbered};
+    use crate::mesh::TriangleMesh;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    #[test]
+    fn test_path_emission() {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            DiffuseLight::new(Color::new(4.0, 2.0, 1.0)),
+        )));
+        let env = SolidEnvironment::new(Color::default());
+        let org = Point3::new(0.0, 0.0, 0.0);
+        let mut sampler = IndependentSampler::new(0);
+
+        let path = PathIntegrator::new(50, 50);
+        let r = Ray::new(org, Vec3::new(0.0, 0.0, -1.0));
+        let light = path.radiance(&r, &world, world.lights(), &env, &mut sampler);
+        assert_eq!(light, Color::new(4.0, 2.0, 1.0));
+
+        let r = Ray::new(org, Vec3::new(0.0, 1.0, 0.0));
+        let miss = path.radiance(&r, &world, world.lights(), &env, &mut sampler);
+        assert_eq!(miss, Color::default());
+    }
+
+    #[test]
+    fn test_path_environment_light() {
+        // A white floor under a uniform sky reflects its albedo, whether the
+        // sky is reached by bouncing or by a shadow ray.
+        let mut world = HittableList::new();
+        world.add(Box::new(TriangleMesh::quad(
+            Point3::new(-1.0, 0.0, 1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -2.0),
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        )));
+        let pixels = vec![Color::new(1.0, 1.0, 1.0); 32 * 16];
+        let env = EquirectEnvironment::new(Arc::new(Image::new(32, 16, pixels).unwrap()));
+
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut sampler = IndependentSampler::new(0);
+        let n = 4000;
+        let mean = (0..n)
+            .map(|_| {
+                PathIntegrator::new(2, 2)
+                    .radiance(&r, &world, world.lights(), &env, &mut sampler)
+                    .x()
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 0.5).abs() < 0.02, "mean was {mean}");
+    }
+
+    #[test]
+    fn test_path_area_light() {
+        // A small sphere of radiance 8 hanging one unit over a floor of albedo
+        // 0.5 fills a cone of sin² = 1/16, so the floor beneath reflects
+        // 0.5 * 8 / 16.
+        let floor = || {
+            Box::new(TriangleMesh::quad(
+                Point3::new(-10.0, 0.0, 10.0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -20.0),
+                Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+            ))
+        };
+        let light = || {
+            Sphere::new(
+                Point3::new(0.0, 1.0, 0.0),
+                0.25,
+                DiffuseLight::new(Color::new(8.0, 8.0, 8.0)),
+            )
+        };
+        let mut sampled = HittableList::new();
+        sampled.add(floor());
+        sampled.add_light(Arc::new(light()));
+        let mut found = HittableList::new();
+        found.add(floor());
+        found.add(Box::new(light()));
+
+        let env = SolidEnvironment::new(Color::default());
+        let r = Ray::new(Point3::new(1.0, 1.0, 0.0), Vec3::new(-1.0, -1.0, 0.0));
+        let stats = |world: &HittableList| {
+            let mut sampler = IndependentSampler::new(1);
+            let n = 4000;
+            let xs: Vec<f64> = (0..n)
+                .map(|_| {
+                    PathIntegrator::new(2, 2)
+                        .radiance(&r, world, world.lights(), &env, &mut sampler)
+                        .x()
+                })
+                .collect();
+            let mean = xs.iter().sum::<f64>() / n as f64;
+            let variance = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+            (mean, variance)
+        };
+
+        let (mean, variance) = stats(&sampled);
+        assert!((mean - 0.25).abs() < 0.01, "mean was {mean}");
+        let (found_mean, found_variance) = stats(&found);
+        assert!((found_mean - 0.25).abs() < 0.05, "mean was {found_mean}");
+        assert!(variance * 20.0 < found_variance);
+    }
+
+    #[test]
+    fn test_path_russian_roulette() {
+        // Glass under a white sky passes on all of it, so every path carries
+        // exactly 1. Roulette ends some early but scales up the others.
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Dielectric::new(1.5),
+        )));
+        let env = SolidEnvironment::new(Color::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.1, 0.2, -1.0));
+        let mut sampler = IndependentSampler::new(0);
+
+        let kept =
+            PathIntegrator::new(50, 50).radiance(&r, &world, world.lights(), &env, &mut sampler);
+        assert!((kept.x() - 1.0).abs() < 1e-12);
+
+        let n = 4000;
+        let xs: Vec<f64> = (0..n)
+            .map(|_| {
+                PathIntegrator::new(50, 0)
+                    .radiance(&r, &world, world.lights(), &env, &mut sampler)
+                    .x()
+            })
+            .collect();
+        let mean = xs.iter().sum::<f64>() / n as f64;
+        assert!((mean - 1.0).abs() < 0.03, "mean was {mean}");
+        assert!(xs.contains(&0.0) && xs.iter().any(|&x| x > 1.0));
+    }
+
+    #[test]
+    fn test_whitted() {
+        // Mirrors are followed to what they reflect, and a diffuse floor
+        // under a sky that can't be sampled sees all of it straight above.
+        let floor = |mat| {
+            let mut world = HittableList::new();
+            world.add(Box::new(TriangleMesh::quad(
+                Point3::new(-1.0, 0.0, 1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -2.0),
+                mat,
+            )));
+            world
+        };
+        let mirror =
+            floor(Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0)) as Arc<dyn Material>);
+        let diffuse =
+            floor(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>);
+        let env = SolidEnvironment::new(Color::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.1, -1.0, 0.0));
+        let mut sampler = IndependentSampler::new(0);
+        let whitted = WhittedIntegrator::new(5);
+        let color = |world: &HittableList, sampler: &mut IndependentSampler| {
+            whitted.radiance(&r, world, world.lights(), &env, sampler)
+        };
+        assert_eq!(color(&mirror, &mut sampler), Color::new(0.8, 0.8, 0.8));
+        assert_eq!(color(&diffuse, &mut sampler), Color::new(0.5, 0.5, 0.5));
+
+        // Lights are sampled with shadow rays, as in `test_path_area_light`.
+        let mut lit =
+            floor(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>);
+        lit.add_light(Arc::new(Sphere::new(
+            Point3::new(0.0, 1.0, 0.0),
+            0.25,
+            DiffuseLight::new(Color::new(8.0, 8.0, 8.0)),
+        )));
+        let dark = SolidEnvironment::new(Color::default());
+        let r = Ray::new(Point3::new(1.0, 1.0, 0.0), Vec3::new(-1.0, -1.0, 0.0));
+        let n = 4000;
+        let mean = (0..n)
+            .map(|_| {
+                whitted
+                    .radiance(&r, &lit, lit.lights(), &dark, &mut sampler)
+                    .x()
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 0.25).abs() < 0.01, "mean was {mean}");
+    }
+
+    #[test]
+    fn test_whitted_glass() {
+        // Glass under a white sky passes on all of it. Following both the
+        // reflected and refracted rays gets that without noise.
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Dielectric::new(1.5),
+        )));
+        let env = SolidEnvironment::new(Color::new(1.0, 1.0, 1.0));
+        let whitted = WhittedIntegrator::new(50);
+        let colors: Vec<_> = (0..10)
+            .map(|seed| {
+                let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.1, 0.2, -1.0));
+                let mut sampler = IndependentSampler::new(seed);
+                whitted.radiance(&r, &world, world.lights(), &env, &mut sampler)
+            })
+            .collect();
+        assert!(colors.iter().all(|&c| c == colors[0]));
+        assert!((colors[0].x() - 1.0).abs() < 0.01, "{:?}", colors[0]);
+    }
+
+    #[test]
+    fn test_ambient_occlusion() {
+        // A point on a floor under a low ceiling is open to the sky only
+        // close to the horizon.
+        let quad = |y: f64| {
+            Box::new(TriangleMesh::quad(
+                Point3::new(-10.0, y, 10.0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -20.0),
+                Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+            ))
+        };
+        let mut world = HittableList::new();
+        world.add(quad(0.0));
+        world.add(quad(1.0));
+        let env = SolidEnvironment::new(Color::default());
+        let r = Ray::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut sampler = IndependentSampler::new(0);
+        let mean = |distance: f64, sampler: &mut IndependentSampler| {
+            let ao = AmbientOcclusionIntegrator::new(distance);
+            (0..1000)
+                .map(|_| ao.radiance(&r, &world, world.lights(), &env, sampler).x())
+                .sum::<f64>()
+                / 1000.0
+        };
+        assert_eq!(mean(0.5, &mut sampler), 1.0);
+        assert!(mean(10.0, &mut sampler) < 0.05);
+
+        let up = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let ao = AmbientOcclusionIntegrator::new(1.0);
+        let sky = ao.radiance(&up, &world, world.lights(), &env, &mut sampler);
+        assert_eq!(sky, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_debug_views() {
+        let albedo = Color::new(0.2, 0.4, 0.6);
+        let shared: Arc<dyn Material> = Arc::new(Numbered::new(1, Lambertian::new(albedo)));
+        let other: Arc<dyn Material> = Arc::new(Numbered::new(2, Dielectric::new(1.5)));
+        let mut world = HittableList::new();
+        for (x, mat) in [(-2.0, &shared), (0.0, &shared), (2.0, &other)] {
+            world.add(Box::new(Sphere::new(
+                Point3::new(x, 0.0, -2.0),
+                0.5,
+                mat.clone(),
+            )));
+        }
+        let env = SolidEnvironment::new(Color::new(1.0, 1.0, 1.0));
+        let mut sampler = IndependentSampler::new(0);
+        let view = |view, r: &Ray, sampler: &mut IndependentSampler| {
+            DebugIntegrator::new(view).radiance(r, &world, world.lights(), &env, sampler)
+        };
+        let org = Point3::new(0.0, 0.0, 0.0);
+        let ahead = Ray::new(org, Vec3::new(0.0, 0.0, -2.0));
+
+        assert_eq!(
+            view(DebugView::Normals, &ahead, &mut sampler),
+            Color::new(0.5, 0.5, 1.0)
+        );
+        // The ray's direction isn't a unit vector, but depth is a distance.
+        assert_eq!(
+            view(DebugView::Depth, &ahead, &mut sampler),
+            Color::new(1.5, 1.5, 1.5)
+        );
+        let i = world.hit(&ahead, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(
+            view(DebugView::Uv, &ahead, &mut sampler),
+            Color::new(i.u, i.v, 0.0)
+        );
+        assert_eq!(
+            view(DebugView::Albedo, &ahead, &mut sampler),
+            Color::new(0.2, 0.4, 0.6)
+        );
+
+        // Objects sharing a material get the same colour.
+        let towards = |x: f64| Ray::new(org, Vec3::new(x, 0.0, -2.0));
+        let ids: Vec<_> = [-2.0, 0.0, 2.0]
+            .iter()
+            .map(|&x| view(DebugView::MaterialId, &towards(x), &mut sampler))
+            .collect();
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[1], ids[2]);
+        // The colour depends only on the number, so it is the same every run.
+        let mut again = HittableList::new();
+        again.add(Box::new(Sphere::new(
+            Point3::new(2.0, 0.0, -2.0),
+            0.5,
+            Numbered::new(2, Lambertian::new(albedo)),
+        )));
+        let id = DebugIntegrator::new(DebugView::MaterialId).radiance(
+            &towards(2.0),
+            &again,
+            again.lights(),
+            &env,
+            &mut sampler,
+        );
+        assert_eq!(id, ids[2]);
+
+        // Misses are black, whatever the sky.
+        let up = Ray::new(org, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            view(DebugView::Normals, &up, &mut sampler),
+            Color::default()
+        );
+    }
+}
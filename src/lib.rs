@@ -1,19 +1,85 @@
+mod aabb;
+mod assets;
+mod bvh;
 mod camera;
+mod decode;
+mod distribution;
+mod environment;
+mod film;
+mod filter;
+mod flat_bvh;
+mod hdr;
 mod hittable;
+mod image;
+mod integrator;
+mod light;
 mod material;
+mod mesh;
+mod obj;
+mod onb;
+mod output;
+mod pdf;
+mod perlin;
 mod ray;
+mod renderer;
+mod sampler;
+mod scene;
 mod sphere;
+mod texture;
+mod tonemap;
+mod triangle;
 mod universe;
 mod utils;
 mod vec3;
 
 use js_sys::Uint8ClampedArray;
-use rand::{rngs::SmallRng, Rng, SeedableRng};
 use wasm_bindgen::prelude::*;
 
-use crate::camera::Camera;
-use crate::hittable::HittableList;
-use crate::vec3::{Color, Point3, Vec3};
+pub use crate::aabb::Aabb;
+pub use crate::assets::register_image;
+pub use crate::bvh::BvhNode;
+pub use crate::camera::{Camera, CameraSettings};
+pub use crate::decode::{decode_image, DecodeError};
+pub use crate::environment::{
+    Environment, EnvironmentSample, EquirectEnvironment, GradientEnvironment, SolidEnvironment,
+};
+pub use crate::film::Adaptive;
+pub use crate::filter::{Filter, FilterKind};
+pub use crate::flat_bvh::{BvhStats, FlatBvh};
+pub use crate::hdr::{decode_hdr, HdrError};
+pub use crate::hittable::{Hittable, HittableList, Intersection};
+pub use crate::image::{Image, ImageError};
+pub use crate::integrator::{
+    AmbientOcclusionIntegrator, DebugIntegrator, DebugView, Integrator, IntegratorKind,
+    PathIntegrator, WhittedIntegrator,
+};
+pub use crate::light::LightList;
+pub use crate::material::{
+    Dielectric, DiffuseLight, Lambertian, Material, Metal, Numbered, Scatter,
+};
+pub use crate::mesh::{MeshError, TriangleMesh};
+pub use crate::obj::{load_obj, ObjError, ObjFile};
+pub use crate::output::{encode_exr, encode_png, encode_ppm, encode_ppm_ascii, EncodeError};
+pub use crate::pdf::{CosinePdf, EnvironmentPdf, HittablePdf, MixturePdf, Pdf, ScatterPdf};
+pub use crate::ray::Ray;
+pub use crate::renderer::Renderer;
+pub use crate::sampler::{
+    AnySampler, HaltonSampler, IndependentSampler, Sampler, SamplerKind, SobolSampler,
+    StratifiedSampler,
+};
+pub use crate::scene::{RenderSettings, Scene, SceneError};
+pub use crate::sphere::Sphere;
+pub use crate::texture::{
+    CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture, WrapMode,
+};
+pub use crate::tonemap::{ToneMap, ToneMapping};
+pub use crate::triangle::Triangle;
+pub use crate::vec3::{Color, Point3, Vec3};
+
+// Starts the Web Worker pool that parallel rendering runs on. The page must
+// await `initThreadPool(navigator.hardwareConcurrency)` before rendering.
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -21,46 +87,99 @@ use crate::vec3::{Color, Point3, Vec3};
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+// The same seed always renders the same image.
 #[wasm_bindgen]
-pub fn render(width: u16, height: u16) -> Result<Uint8ClampedArray, JsValue> {
-    let mut rng =
-        SmallRng::from_rng(rand::thread_rng()).map_err(|e| JsValue::from(format!("{e}")))?;
+pub fn render(width: u16, height: u16, seed: u32) -> Uint8ClampedArray {
+    let mut renderer = Renderer::random(width, height, seed);
+    renderer.render_pass(RenderSettings::default().samples_per_pixel, None)
+}
 
-    // World
-    let world = HittableList::random_scene().map_err(|e| JsValue::from(format!("{e}")))?;
+// Renders a scene described in the JSON format read by `Scene::from_json`.
+#[wasm_bindgen]
+pub fn render_scene(
+    json: &str,
+    width: u16,
+    height: u16,
+    seed: u32,
+) -> Result<Uint8ClampedArray, JsValue> {
+    utils::set_panic_hook();
 
-    let samples_per_pixel = 10;
-    let max_depth = 50;
+    let scene = Scene::from_json(json).map_err(|e| JsValue::from(format!("{e}")))?;
+    let samples = scene.settings.samples_per_pixel;
+    let mut renderer = Renderer::with_scene(scene, width, height, seed as u64);
+    Ok(renderer.render_pass(samples, None))
+}
 
-    // Camera
-    let cam = Camera::new(
-        Point3::new(13.0, 2.0, 3.0),
-        Point3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        20.0,
-        width as f64 / height as f64,
-        0.1,
-        10.0,
-    );
+// Renders an OBJ model, given as a string or `Uint8Array`, with the MTL
+// library it uses if there is one. The camera is placed to frame the model.
+#[wasm_bindgen]
+pub fn render_obj(
+    obj: JsValue,
+    mtl: JsValue,
+    width: u16,
+    height: u16,
+    seed: u32,
+) -> Result<Uint8ClampedArray, JsValue> {
+    let mut renderer = Renderer::from_obj(obj, mtl, width, height, seed)?;
+    Ok(renderer.render_pass(RenderSettings::default().samples_per_pixel, None))
+}
 
-    let mut img = vec![];
+// Decodes a Radiance .hdr environment map and keeps it under `name`, for
+// scenes to use as `"background": { "type": "equirect", "image": name }`.
+#[wasm_bindgen]
+pub fn load_hdr(name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+    utils::set_panic_hook();
 
-    for j in (0..height).rev() {
-        for i in 0..width {
-            let color = (0..samples_per_pixel)
-                .map(|_| {
-                    let (u, v) = (
-                        (i as f64 + rng.gen_range(0.0..1.0)) / (width as f64 - 1.0),
-                        (j as f64 + rng.gen_range(0.0..1.0)) / (height as f64 - 1.0),
-                    );
-                    let r = cam.ray(u, v);
-                    r.color(&world, max_depth)
-                })
-                .fold(Color::default(), |acc, c| acc + c);
+    let image = decode_hdr(bytes).map_err(|e| JsValue::from(format!("{e}")))?;
+    assets::register_image(name, image);
+    Ok(())
+}
 
-            img.extend::<Vec<u8>>((color / samples_per_pixel as f64).into());
-        }
-    }
+// Decodes a PNG or JPEG image and keeps it under `name`, for scenes to use as
+// `{ "type": "image", "image": name }` textures.
+#[wasm_bindgen]
+pub fn load_image(name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+    utils::set_panic_hook();
 
-    Ok(img[..].into())
+    let image = decode_image(bytes).map_err(|e| JsValue::from(format!("{e}")))?;
+    assets::register_image(name, image);
+    Ok(())
+}
+
+// Encodes RGBA bytes, such as a canvas the page composited from tiles, as a
+// PNG file.
+#[wasm_bindgen]
+pub fn png_from_rgba(width: u16, height: u16, rgba: &[u8]) -> Result<Vec<u8>, JsValue> {
+    check_rgba(width, height, rgba)?;
+    encode_png(width, height, rgba).map_err(|e| JsValue::from(format!("{e}")))
+}
+
+// As for `png_from_rgba`, but a plain text (P3) or binary (P6) PPM file.
+#[wasm_bindgen]
+pub fn ppm_from_rgba(
+    width: u16,
+    height: u16,
+    rgba: &[u8],
+    ascii: bool,
+) -> Result<Vec<u8>, JsValue> {
+    check_rgba(width, height, rgba)?;
+    let ppm = if ascii {
+        encode_ppm_ascii(width, height, rgba)
+    } else {
+        encode_ppm(width, height, rgba)
+    };
+    ppm.map_err(|e| JsValue::from(format!("{e}")))
+}
+
+fn check_rgba(width: u16, height: u16, rgba: &[u8]) -> Result<(), JsValue> {
+    if width == 0 || height == 0 {
+        return Err(JsValue::from(format!("a {width}x{height} image is empty")));
+    }
+    if rgba.len() != width as usize * height as usize * 4 {
+        return Err(JsValue::from(format!(
+            "{} bytes is not a {width}x{height} RGBA image",
+            rgba.len()
+        )));
+    }
+    Ok(())
 }
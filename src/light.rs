@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{Hittable, Intersection};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+// The objects made of emissive materials. The renderer aims shadow rays at
+// them from diffuse surfaces instead of waiting for bounces to find them.
+#[derive(Clone, Default)]
+pub struct LightList(Vec<Arc<dyn Hittable>>);
+
+impl LightList {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn add(&mut self, light: Arc<dyn Hittable>) {
+        self.0.push(light)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Hittable for LightList {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        let mut result = None;
+        let mut t_closest = t_max;
+        for light in &self.0 {
+            if let Some(rec) = light.hit(r, t_min, t_closest) {
+                t_closest = rec.t;
+                result = Some(rec);
+            }
+        }
+        result
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.iter().fold(Aabb::empty(), |acc, light| {
+            acc.surrounding(&light.bounding_box())
+        })
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.0.iter().any(|light| light.occluded(r, t_min, t_max))
+    }
+
+    // Picks a light uniformly with the first half of `u`, then reuses where
+    // it fell within that light's share to pick a direction towards it.
+    fn sample(&self, origin: Point3, (u1, u2): (f64, f64)) -> Option<Vec3> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let x = u1 * self.0.len() as f64;
+        let i = (x as usize).min(self.0.len() - 1);
+        self.0[i].sample(origin, ((x - i as f64).min(1.0), u2))
+    }
+
+    // Every light that `dir` reaches could have been picked, even those
+    // behind others.
+    fn pdf(&self, origin: Point3, dir: Vec3) -> f64 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.0.iter().map(|light| light.pdf(origin, dir)).sum();
+        sum / self.0.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    use super::LightList;
+    use crate::hittable::Hittable;
+    use crate::material::DiffuseLight;
+    use crate::mesh::TriangleMesh;
+    use crate::ray::Ray;
+    use crate::sampler::{IndependentSampler, Sampler};
+    use crate::sphere::Sphere;
+    use crate::triangle::Triangle;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn lights() -> Vec<Arc<dyn Hittable>> {
+        let mat = || DiffuseLight::new(Color::new(1.0, 1.0, 1.0));
+        vec![
+            Arc::new(Sphere::new(Point3::new(0.0, 2.0, 0.0), 0.5, mat())),
+            Arc::new(Triangle::new(
+                Point3::new(-1.0, 1.0, -1.0),
+                Point3::new(1.0, 1.0, -1.0),
+                Point3::new(0.0, 1.5, 1.0),
+                mat(),
+            )),
+            Arc::new(TriangleMesh::quad(
+                Point3::new(-1.0, 0.5, -3.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.5),
+                mat(),
+            )),
+        ]
+    }
+
+    #[test]
+    fn test_sample_pdf() {
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let mut sampler = IndependentSampler::new(0);
+        let mut list = LightList::new();
+        for light in lights() {
+            list.add(light);
+        }
+        let mut lights = lights();
+        lights.push(Arc::new(list));
+
+        for light in lights {
+            // Sampled directions reach the light, with the density the light
+            // reports for them.
+            for _ in 0..100 {
+                let dir = light.sample(origin, sampler.get_2d()).unwrap();
+                assert!((dir.length() - 1.0).abs() < 1e-12);
+                assert!(light
+                    .hit(&Ray::new(origin, dir), 0.001, f64::INFINITY)
+                    .is_some());
+                assert!(light.pdf(origin, dir) > 0.0);
+            }
+
+            // The density integrates to one over the sphere of directions.
+            let n = 200_000;
+            let integral = (0..n)
+                .map(|_| {
+                    let dir = Vec3::random_unit_vector(&mut sampler);
+                    4.0 * PI * light.pdf(origin, dir)
+                })
+                .sum::<f64>()
+                / n as f64;
+            assert!((integral - 1.0).abs() < 0.03, "integral was {integral}");
+        }
+    }
+
+    #[test]
+    fn test_light_list() {
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let mut list = LightList::new();
+        assert!(list.sample(origin, (0.5, 0.5)).is_none());
+        assert_eq!(list.pdf(origin, up), 0.0);
+
+        let lights = lights();
+        for light in &lights {
+            list.add(light.clone());
+        }
+        assert_eq!(list.len(), 3);
+
+        // Straight up passes through the triangle before reaching the sphere.
+        // Either could have been picked, but the triangle is what is hit.
+        let sphere = lights[0].pdf(origin, up);
+        let triangle = lights[1].pdf(origin, up);
+        assert!((list.pdf(origin, up) - (sphere + triangle) / 3.0).abs() < 1e-12);
+        let r = Ray::new(origin, up);
+        let t = lights[1].hit(&r, 0.001, f64::INFINITY).unwrap().t;
+        assert_eq!(list.hit(&r, 0.001, f64::INFINITY).unwrap().t, t);
+        assert!(list.occluded(&r, 0.001, t + 0.001));
+        assert!(!list.occluded(&r, 0.001, t - 0.001));
+
+        // The last light is picked by the top of the range.
+        let dir = list.sample(origin, (0.9, 0.6)).unwrap();
+        assert!(lights[2]
+            .hit(&Ray::new(origin, dir), 0.001, f64::INFINITY)
+            .is_some());
+    }
+}
@@ -1,31 +1,175 @@
-use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::sync::Arc;
 
 use crate::hittable::Intersection;
+use crate::pdf::{CosinePdf, ScatterPdf};
 use crate::ray::Ray;
-use crate::vec3::{Color, Vec3};
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
+use crate::vec3::{Color, Point3, Vec3};
 
-pub trait Material {
-    fn scatter(&self, r_in: &Ray, i: Intersection) -> Option<(Color, Ray)>;
+// How light arriving at a surface leaves it.
+pub enum Scatter {
+    // In a single direction, as off a mirror or through glass, scaled by
+    // `attenuation`. Light sampling can never pick that direction, so it is
+    // followed as is.
+    Specular { attenuation: Color, ray: Ray },
+    // Spread over many directions. `pdf` picks them, and `Material::eval`
+    // gives the BSDF towards any direction, so the renderer can weigh each
+    // one by how likely it was.
+    Diffuse { pdf: ScatterPdf },
+}
+
+// Where a smooth surface sends the light arriving along a ray: a reflected
+// ray and, unless all of it is reflected, a refracted one, each with the
+// share of the light it carries.
+pub struct Split {
+    pub reflected: (Color, Ray),
+    pub refracted: Option<(Color, Ray)>,
+}
+
+// Shared by every thread when rendering in parallel.
+pub trait Material: Send + Sync {
+    fn scatter(&self, r_in: &Ray, i: &Intersection, sampler: &mut dyn Sampler) -> Option<Scatter>;
+
+    fn emitted(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        Color::default()
+    }
+
+    // Whether `emitted` is ever non-black, so the objects made of this are
+    // sampled as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // For materials with a diffuse scatter, the BSDF times the cosine
+    // towards `dir`.
+    fn eval(&self, _r_in: &Ray, _i: &Intersection, _dir: Vec3) -> Color {
+        Color::default()
+    }
+
+    // For smooth surfaces that both reflect and refract, each ray they send
+    // light along, so that Whitted tracing can follow both instead of the one
+    // `scatter` picks.
+    fn split(&self, _r_in: &Ray, _i: &Intersection) -> Option<Split> {
+        None
+    }
+
+    // The colour the surface tints light with, for the albedo debug view.
+    fn albedo(&self, _i: &Intersection) -> Color {
+        Color::default()
+    }
+
+    // Tells materials apart for the material id debug view. Scenes number
+    // theirs with `Numbered`; any others share 0.
+    fn id(&self) -> usize {
+        0
+    }
+}
+
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, r_in: &Ray, i: &Intersection, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        (**self).scatter(r_in, i, sampler)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: Point3) -> Color {
+        (**self).emitted(u, v, p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        (**self).is_emissive()
+    }
+
+    fn eval(&self, r_in: &Ray, i: &Intersection, dir: Vec3) -> Color {
+        (**self).eval(r_in, i, dir)
+    }
+
+    fn split(&self, r_in: &Ray, i: &Intersection) -> Option<Split> {
+        (**self).split(r_in, i)
+    }
+
+    fn albedo(&self, i: &Intersection) -> Color {
+        (**self).albedo(i)
+    }
+
+    fn id(&self) -> usize {
+        (**self).id()
+    }
+}
+
+// A material with the number the scene that made it gave it, such as its
+// place in load order, so it is coloured the same way on every run.
+pub struct Numbered<M: Material> {
+    id: usize,
+    mat: M,
+}
+
+impl<M: Material> Numbered<M> {
+    pub fn new(id: usize, mat: M) -> Self {
+        Self { id, mat }
+    }
+}
+
+impl<M: Material> Material for Numbered<M> {
+    fn scatter(&self, r_in: &Ray, i: &Intersection, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        self.mat.scatter(r_in, i, sampler)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: Point3) -> Color {
+        self.mat.emitted(u, v, p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.mat.is_emissive()
+    }
+
+    fn eval(&self, r_in: &Ray, i: &Intersection, dir: Vec3) -> Color {
+        self.mat.eval(r_in, i, dir)
+    }
+
+    fn split(&self, r_in: &Ray, i: &Intersection) -> Option<Split> {
+        self.mat.split(r_in, i)
+    }
+
+    fn albedo(&self, i: &Intersection) -> Color {
+        self.mat.albedo(i)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
         Self { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _: &Ray, i: Intersection) -> Option<(Color, Ray)> {
-        let scatter_direction = match i.normal + Vec3::random_unit_vector() {
-            dir if dir.near_zero() => i.normal,
-            dir => dir,
-        };
+    // Cosine-weighted sampling cancels the BSDF's cosine, leaving just the
+    // albedo as each bounce's weight.
+    fn scatter(&self, _: &Ray, i: &Intersection, _: &mut dyn Sampler) -> Option<Scatter> {
+        Some(Scatter::Diffuse {
+            pdf: ScatterPdf::Cosine(CosinePdf::new(i.normal)),
+        })
+    }
+
+    fn eval(&self, _: &Ray, i: &Intersection, dir: Vec3) -> Color {
+        let cosine = i.normal.dot(dir.unit()).max(0.0);
+        self.albedo.value(i.u, i.v, i.p) * (cosine / PI)
+    }
 
-        Some((self.albedo, Ray::new(i.p, scatter_direction)))
+    fn albedo(&self, i: &Intersection) -> Color {
+        self.albedo.value(i.u, i.v, i.p)
     }
 }
 
@@ -41,19 +185,23 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, i: Intersection) -> Option<(Color, Ray)> {
+    fn scatter(&self, r_in: &Ray, i: &Intersection, sampler: &mut dyn Sampler) -> Option<Scatter> {
         let reflected = r_in.direction().unit().reflect(i.normal);
-        let out = (
-            self.albedo,
-            Ray::new(i.p, reflected + self.fuzz * Vec3::random_in_unit_sphere()),
-        );
+        let dir = reflected + self.fuzz * Vec3::random_in_unit_sphere(sampler);
 
-        if out.1.direction().dot(i.normal) > 0.0 {
-            Some(out)
+        if dir.dot(i.normal) > 0.0 {
+            Some(Scatter::Specular {
+                attenuation: self.albedo,
+                ray: Ray::new(i.p, dir),
+            })
         } else {
             None
         }
     }
+
+    fn albedo(&self, _: &Intersection) -> Color {
+        self.albedo
+    }
 }
 
 pub struct Dielectric {
@@ -72,16 +220,14 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, i: Intersection) -> Option<(Color, Ray)> {
+    fn scatter(&self, r_in: &Ray, i: &Intersection, sampler: &mut dyn Sampler) -> Option<Scatter> {
         let refraction_ratio = if i.front_face { 1.0 / self.ir } else { self.ir };
 
         let unit_direction = r_in.direction().unit();
         let cos_theta = (-unit_direction).dot(i.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let is_reflective = SmallRng::from_rng(rand::thread_rng())
-            .map(|mut rng| Self::reflectance(cos_theta, refraction_ratio) > rng.gen_range(0.0..1.0))
-            .unwrap_or(false);
+        let is_reflective = Self::reflectance(cos_theta, refraction_ratio) > sampler.get_1d();
 
         let direction = if refraction_ratio * sin_theta > 1.0 || is_reflective {
             unit_direction.reflect(i.normal)
@@ -89,6 +235,60 @@ impl Material for Dielectric {
             unit_direction.refract(i.normal, refraction_ratio)
         };
 
-        Some((Color::new(1.0, 1.0, 1.0), Ray::new(i.p, direction)))
+        Some(Scatter::Specular {
+            attenuation: Color::new(1.0, 1.0, 1.0),
+            ray: Ray::new(i.p, direction),
+        })
+    }
+
+    // `scatter` picks between these with the reflectance as the odds.
+    fn split(&self, r_in: &Ray, i: &Intersection) -> Option<Split> {
+        let refraction_ratio = if i.front_face { 1.0 / self.ir } else { self.ir };
+
+        let unit_direction = r_in.direction().unit();
+        let cos_theta = (-unit_direction).dot(i.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let reflected = Ray::new(i.p, unit_direction.reflect(i.normal));
+        if refraction_ratio * sin_theta > 1.0 {
+            return Some(Split {
+                reflected: (Color::new(1.0, 1.0, 1.0), reflected),
+                refracted: None,
+            });
+        }
+        let r = Self::reflectance(cos_theta, refraction_ratio);
+        let refracted = Ray::new(i.p, unit_direction.refract(i.normal, refraction_ratio));
+        Some(Split {
+            reflected: (Color::new(r, r, r), reflected),
+            refracted: Some((Color::new(1.0 - r, 1.0 - r, 1.0 - r), refracted)),
+        })
+    }
+
+    fn albedo(&self, _: &Intersection) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _: &Ray, _: &Intersection, _: &mut dyn Sampler) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.emit
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
     }
 }
@@ -0,0 +1,288 @@
+use std::fmt;
+
+use crate::aabb::Aabb;
+use crate::distribution::Distribution1D;
+use crate::flat_bvh::{BvhStats, BvhTree};
+use crate::hittable::{Hittable, Intersection};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::triangle;
+use crate::vec3::{Point3, Vec3};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshError {
+    // A per-vertex buffer that is neither empty nor one entry per position.
+    AttributeLength {
+        attribute: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    IndexOutOfRange {
+        triangle: usize,
+        index: u32,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AttributeLength {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mesh has {found} {attribute}, expected none or one per position ({expected})"
+            ),
+            Self::IndexOutOfRange { triangle, index } => {
+                write!(f, "triangle {triangle} refers to missing vertex {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+// An indexed triangle mesh. Normals and texture coordinates are optional, but
+// when present there is one per position and they are interpolated across
+// each triangle.
+pub struct TriangleMesh<M: Material> {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    indices: Vec<[u32; 3]>,
+    tree: BvhTree,
+    // Picks triangles in proportion to their area, for sampling the mesh as
+    // a light.
+    areas: Distribution1D,
+    area: f64,
+    mat: M,
+}
+
+impl<M: Material> TriangleMesh<M> {
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        indices: Vec<[u32; 3]>,
+        mat: M,
+    ) -> Result<Self, MeshError> {
+        for (attribute, found) in [("normals", normals.len()), ("uvs", uvs.len())] {
+            if found != 0 && found != positions.len() {
+                return Err(MeshError::AttributeLength {
+                    attribute,
+                    expected: positions.len(),
+                    found,
+                });
+            }
+        }
+
+        for (triangle, tri) in indices.iter().enumerate() {
+            if let Some(&index) = tri.iter().find(|&&i| i as usize >= positions.len()) {
+                return Err(MeshError::IndexOutOfRange { triangle, index });
+            }
+        }
+
+        let bounds: Vec<Aabb> = indices
+            .iter()
+            .map(|tri| triangle::bounding_box(tri.map(|i| positions[i as usize])))
+            .collect();
+        let (tree, order) = BvhTree::build(&bounds);
+        let indices: Vec<[u32; 3]> = order.iter().map(|&i| indices[i]).collect();
+
+        let areas: Vec<f64> = indices
+            .iter()
+            .map(|tri| triangle::area(tri.map(|i| positions[i as usize])))
+            .collect();
+        let area = areas.iter().sum();
+
+        Ok(Self {
+            positions,
+            normals,
+            uvs,
+            indices,
+            tree,
+            areas: Distribution1D::new(areas),
+            area,
+            mat,
+        })
+    }
+
+    // A parallelogram with corner `q` and edges `u` and `v`, split into two
+    // triangles.
+    pub fn quad(q: Point3, u: Vec3, v: Vec3, mat: M) -> Self {
+        Self::new(
+            vec![q, q + u, q + u + v, q + v],
+            vec![],
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![[0, 1, 2], [0, 2, 3]],
+            mat,
+        )
+        .expect("a quad's buffers are consistent")
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.tree.stats()
+    }
+
+    fn vertices(&self, i: usize) -> [Point3; 3] {
+        self.indices[i].map(|i| self.positions[i as usize])
+    }
+
+    fn hit_triangle(&self, i: usize, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        let tri = self.indices[i].map(|i| i as usize);
+        let [a, b, c] = self.vertices(i);
+        let (t, b1, b2) = triangle::intersect(r, [a, b, c], t_min, t_max)?;
+        let b0 = 1.0 - b1 - b2;
+
+        let geometric = (b - a).cross(c - a).unit();
+        let shading = if self.normals.is_empty() {
+            geometric
+        } else {
+            let [na, nb, nc] = tri.map(|i| self.normals[i]);
+            match b0 * na + b1 * nb + b2 * nc {
+                n if n.near_zero() => geometric,
+                n => n.unit(),
+            }
+        };
+        let (normal, front_face) = triangle::face_normal(r, geometric, shading);
+
+        let uv = if self.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let [ta, tb, tc] = tri.map(|i| self.uvs[i]);
+            (
+                b0 * ta.0 + b1 * tb.0 + b2 * tc.0,
+                b0 * ta.1 + b1 * tb.1 + b2 * tc.1,
+            )
+        };
+
+        Some(Intersection::new(
+            r.at(t),
+            normal,
+            &self.mat,
+            t,
+            uv,
+            front_face,
+        ))
+    }
+}
+
+impl<M: Material + 'static> Hittable for TriangleMesh<M> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        self.tree.traverse(r, t_min, t_max, |i, t_closest| {
+            self.hit_triangle(i, r, t_min, t_closest)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.tree.bounding_box()
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.tree.any(r, t_min, t_max, |i| {
+            triangle::intersect(r, self.vertices(i), t_min, t_max).is_some()
+        })
+    }
+
+    fn sample(&self, origin: Point3, (u1, u2): (f64, f64)) -> Option<Vec3> {
+        if self.indices.is_empty() || self.area <= 0.0 {
+            return None;
+        }
+        // Reuse where `u1` fell within the chosen triangle's share.
+        let (x, _, i) = self.areas.sample(u1);
+        let u1 = (x * self.areas.len() as f64 - i as f64).clamp(0.0, 1.0);
+        let p = triangle::sample_point(self.vertices(i), (u1, u2));
+        triangle::towards(origin, p)
+    }
+
+    fn pdf(&self, origin: Point3, dir: Vec3) -> f64 {
+        if self.area <= 0.0 {
+            return 0.0;
+        }
+        let r = Ray::new(origin, dir);
+        let mut closest = None;
+        self.tree
+            .traverse(&r, 0.001, f64::INFINITY, |i, t_closest| {
+                let hit = self.hit_triangle(i, &r, 0.001, t_closest)?;
+                closest = Some((i, hit.t));
+                Some(hit)
+            });
+        let Some((i, t)) = closest else {
+            return 0.0;
+        };
+        let [a, b, c] = self.vertices(i);
+        let normal = (b - a).cross(c - a).unit();
+        triangle::solid_angle_pdf(&r, t, normal, self.area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MeshError, TriangleMesh};
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn quad(normals: Vec<Vec3>) -> Result<TriangleMesh<Lambertian>, MeshError> {
+        TriangleMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals,
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![[0, 1, 2], [0, 2, 3]],
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn test_hit_interpolates_attributes() {
+        let tilted = Vec3::new(1.0, 0.0, 1.0).unit();
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let mesh = quad(vec![up, tilted, tilted, up]).unwrap();
+
+        let r = Ray::new(Point3::new(0.5, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let i = mesh.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((i.t - 1.0).abs() < 1e-12);
+        assert!((i.u - 0.5).abs() < 1e-12 && (i.v - 0.25).abs() < 1e-12);
+        assert!(i.front_face);
+
+        let want = (0.5 * up + 0.5 * tilted).unit();
+        assert!((i.normal - want).near_zero());
+
+        let miss = Ray::new(Point3::new(1.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&miss, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_invalid_buffers() {
+        assert_eq!(
+            quad(vec![Vec3::new(0.0, 0.0, 1.0)]).err(),
+            Some(MeshError::AttributeLength {
+                attribute: "normals",
+                expected: 4,
+                found: 1
+            })
+        );
+
+        let mesh = TriangleMesh::new(
+            vec![Point3::new(0.0, 0.0, 0.0)],
+            vec![],
+            vec![],
+            vec![[0, 0, 3]],
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        );
+        assert_eq!(
+            mesh.err(),
+            Some(MeshError::IndexOutOfRange {
+                triangle: 0,
+                index: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::Arc;
+
+use crate::hittable::HittableList;
+use crate::material::{Dielectric, Lambertian, Material, Metal, Numbered};
+use crate::mesh::TriangleMesh;
+use crate::vec3::{Color, Point3, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjFile {
+    Obj,
+    Mtl,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjError {
+    pub file: ObjFile,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = match self.file {
+            ObjFile::Obj => "OBJ",
+            ObjFile::Mtl => "MTL",
+        };
+        write!(f, "{file} line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// Parses an OBJ model, and optionally the MTL library it uses, into one
+// triangle mesh per group and material. Polygons are split into fans.
+pub fn load_obj(obj: &str, mtl: Option<&str>) -> Result<HittableList, ObjError> {
+    load_numbered_obj(obj, mtl, &mut 0)
+}
+
+// Like `load_obj`, but numbers the model's materials on from `next_id`, so a
+// scene can keep them apart from its own.
+pub(crate) fn load_numbered_obj(
+    obj: &str,
+    mtl: Option<&str>,
+    next_id: &mut usize,
+) -> Result<HittableList, ObjError> {
+    let default_mat: Arc<dyn Material> =
+        Arc::new(Numbered::new(*next_id, Lambertian::new(DEFAULT_DIFFUSE)));
+    *next_id += 1;
+    let materials = match mtl {
+        Some(mtl) => parse_mtl(mtl, next_id)?,
+        None => HashMap::new(),
+    };
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+
+    let mut meshes: Vec<MeshBuilder> = vec![];
+    let mut current: HashMap<(String, String), usize> = HashMap::new();
+    let (mut group, mut mat_name) = (String::new(), String::new());
+
+    for (n, line) in obj.lines().enumerate() {
+        let mut p = Parser::new(ObjFile::Obj, n + 1, line);
+        let keyword = match p.keyword() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        match keyword {
+            "v" => positions.push(p.vec3()?),
+            "vn" => normals.push(p.vec3()?),
+            "vt" => {
+                let u = p.number()?;
+                let v = p.optional_number()?.unwrap_or(0.0);
+                uvs.push((u, v));
+            }
+            "g" | "o" => group = p.rest().to_string(),
+            "usemtl" => {
+                let name = p.rest();
+                if mtl.is_some() && !materials.contains_key(name) {
+                    return Err(p.error(format!("unknown material \"{name}\"")));
+                }
+                mat_name = name.to_string();
+            }
+            "f" => {
+                let words: Vec<&str> = p.words().collect();
+                let corners = words
+                    .iter()
+                    .map(|word| {
+                        parse_corner(word, [positions.len(), uvs.len(), normals.len()])
+                            .map_err(|message| p.error(message))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if corners.len() < 3 {
+                    return Err(p.error(format!("face has {} vertices", corners.len())));
+                }
+
+                let key = (group.clone(), mat_name.clone());
+                let mesh = *current.entry(key).or_insert_with(|| {
+                    meshes.push(MeshBuilder::new(&mat_name));
+                    meshes.len() - 1
+                });
+                let mesh = &mut meshes[mesh];
+                let corners: Vec<u32> = corners.into_iter().map(|c| mesh.vertex(c)).collect();
+                for i in 1..corners.len() - 1 {
+                    mesh.triangles
+                        .push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut world = HittableList::new();
+    for mesh in meshes {
+        let mat = materials
+            .get(&mesh.mat_name)
+            .cloned()
+            .unwrap_or_else(|| default_mat.clone());
+        world.add(Box::new(mesh.build(&positions, &uvs, &normals, mat)));
+    }
+
+    Ok(world)
+}
+
+const DEFAULT_DIFFUSE: Color = Color::new(0.8, 0.8, 0.8);
+
+// The subset of an MTL definition that maps onto our materials.
+struct MtlMaterial {
+    diffuse: Color,
+    specular: Color,
+    shininess: f64,
+    ior: f64,
+    dissolve: f64,
+    illum: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: DEFAULT_DIFFUSE,
+            specular: Color::default(),
+            shininess: 0.0,
+            ior: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn build(&self) -> Arc<dyn Material> {
+        let glass = matches!(self.illum, 4 | 6 | 7 | 9) || self.dissolve < 1.0;
+        let mirror = matches!(self.illum, 3 | 5) || self.diffuse.near_zero();
+
+        if glass {
+            Arc::new(Dielectric::new(self.ior))
+        } else if mirror && !self.specular.near_zero() {
+            // Map the Phong exponent onto a roughness in [0, 1].
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(self.specular, fuzz))
+        } else {
+            Arc::new(Lambertian::new(self.diffuse))
+        }
+    }
+}
+
+fn parse_mtl(
+    mtl: &str,
+    next_id: &mut usize,
+) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let mut defs: Vec<(String, MtlMaterial)> = vec![];
+
+    for (n, line) in mtl.lines().enumerate() {
+        let mut p = Parser::new(ObjFile::Mtl, n + 1, line);
+        let keyword = match p.keyword() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            defs.push((p.rest().to_string(), MtlMaterial::default()));
+            continue;
+        }
+
+        let def = match defs.last_mut() {
+            Some((_, def)) => def,
+            None if matches!(keyword, "Kd" | "Ks" | "Ns" | "Ni" | "d" | "Tr" | "illum") => {
+                return Err(p.error(format!("\"{keyword}\" before any newmtl")))
+            }
+            None => continue,
+        };
+
+        match keyword {
+            "Kd" => def.diffuse = p.vec3()?,
+            "Ks" => def.specular = p.vec3()?,
+            "Ns" => def.shininess = p.number()?,
+            "Ni" => def.ior = p.number()?,
+            "d" => def.dissolve = p.number()?,
+            "Tr" => def.dissolve = 1.0 - p.number::<f64>()?,
+            "illum" => def.illum = p.number()?,
+            _ => {}
+        }
+    }
+
+    // Numbered in load order, after the default material.
+    Ok(defs
+        .into_iter()
+        .map(|(name, def)| {
+            let mat: Arc<dyn Material> = Arc::new(Numbered::new(*next_id, def.build()));
+            *next_id += 1;
+            (name, mat)
+        })
+        .collect())
+}
+
+// Resolves a face corner such as `3`, `3/1`, `3//2` or `-1/-1/-1` into
+// zero-based (position, uv, normal) indices.
+fn parse_corner(word: &str, counts: [usize; 3]) -> Result<Corner, String> {
+    let mut parts = word.split('/');
+    let mut index = |kind: &str, count: usize, required: bool| -> Result<Option<usize>, String> {
+        let s = match parts.next() {
+            Some(s) if !s.is_empty() => s,
+            _ if required => return Err(format!("face vertex \"{word}\" has no position")),
+            _ => return Ok(None),
+        };
+
+        let i: i64 = s
+            .parse()
+            .map_err(|_| format!("invalid {kind} index \"{s}\""))?;
+        let resolved = match i {
+            i if i > 0 => i - 1,
+            i if i < 0 => count as i64 + i,
+            _ => return Err(format!("{kind} index must not be 0")),
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(format!("{kind} index {i} out of range ({count} defined)"));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = index("position", counts[0], true)?.unwrap_or_default();
+    let uv = index("texture coordinate", counts[1], false)?;
+    let normal = index("normal", counts[2], false)?;
+    Ok(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct MeshBuilder {
+    mat_name: String,
+    vertices: Vec<Corner>,
+    lookup: HashMap<Corner, u32>,
+    triangles: Vec<[u32; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(mat_name: &str) -> Self {
+        Self {
+            mat_name: mat_name.to_string(),
+            vertices: vec![],
+            lookup: HashMap::new(),
+            triangles: vec![],
+        }
+    }
+
+    fn vertex(&mut self, corner: Corner) -> u32 {
+        let vertices = &mut self.vertices;
+        *self.lookup.entry(corner).or_insert_with(|| {
+            vertices.push(corner);
+            vertices.len() as u32 - 1
+        })
+    }
+
+    fn build(
+        self,
+        positions: &[Point3],
+        uvs: &[(f64, f64)],
+        normals: &[Vec3],
+        mat: Arc<dyn Material>,
+    ) -> TriangleMesh<Arc<dyn Material>> {
+        // Attributes are only kept if every vertex of the mesh has them.
+        let mesh_normals = self
+            .vertices
+            .iter()
+            .map(|c| c.normal.map(|i| normals[i]))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let mesh_uvs = self
+            .vertices
+            .iter()
+            .map(|c| c.uv.map(|i| uvs[i]))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let mesh_positions = self
+            .vertices
+            .iter()
+            .map(|c| positions[c.position])
+            .collect();
+
+        TriangleMesh::new(mesh_positions, mesh_normals, mesh_uvs, self.triangles, mat)
+            .expect("OBJ corners are resolved against the buffers they index")
+    }
+}
+
+struct Parser<'a> {
+    file: ObjFile,
+    line: usize,
+    text: &'a str,
+    words: SplitWhitespace<'a>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(file: ObjFile, line: usize, text: &'a str) -> Self {
+        let text = text.split('#').next().unwrap_or_default().trim();
+        Self {
+            file,
+            line,
+            text,
+            words: text.split_whitespace(),
+        }
+    }
+
+    fn error(&self, message: String) -> ObjError {
+        ObjError {
+            file: self.file,
+            line: self.line,
+            message,
+        }
+    }
+
+    fn keyword(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    fn words(&mut self) -> impl Iterator<Item = &'a str> + '_ {
+        &mut self.words
+    }
+
+    // Everything after the keyword, for names that may contain spaces.
+    fn rest(&self) -> &'a str {
+        self.text
+            .split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest.trim())
+    }
+
+    fn optional_number<T: FromStr>(&mut self) -> Result<Option<T>, ObjError> {
+        match self.words.next() {
+            Some(word) => word
+                .parse()
+                .map(Some)
+                .map_err(|_| self.error(format!("invalid number \"{word}\""))),
+            None => Ok(None),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, ObjError> {
+        self.optional_number()?
+            .ok_or_else(|| self.error("missing number".to_string()))
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, ObjError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_obj, ObjError, ObjFile};
+    use crate::camera::Camera;
+    use crate::hittable::Hittable;
+    use crate::ray::Ray;
+    use crate::vec3::{Point3, Vec3};
+
+    const QUAD: &str = "
+# a unit square split into two groups
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 2 0 0
+v 2 1 0
+vn 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+
+g left
+usemtl matte
+f 1/1/1 2/2/1 3/3/1 4/4/1
+
+g right
+usemtl glass
+f -5//1 -2//1 -1//1 -4//1
+";
+
+    const MTL: &str = "
+newmtl matte
+Kd 0.5 0.2 0.1
+
+newmtl glass
+Ni 1.33
+illum 7
+";
+
+    #[test]
+    fn test_load() {
+        let world = load_obj(QUAD, Some(MTL)).unwrap();
+
+        let r = Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let i = world.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((i.t - 1.0).abs() < 1e-12);
+        assert!((i.u - 0.25).abs() < 1e-12 && (i.v - 0.75).abs() < 1e-12);
+        assert_eq!(i.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let r = Ray::new(Point3::new(1.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(world.hit(&r, 0.001, f64::INFINITY).is_some());
+
+        let r = Ray::new(Point3::new(2.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(world.hit(&r, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_load_without_mtl() {
+        let world = load_obj(QUAD, None).unwrap();
+        let r = Ray::new(Point3::new(1.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(world.hit(&r, 0.001, f64::INFINITY).is_some());
+    }
+
+    #[test]
+    fn test_framing() {
+        let world = load_obj(QUAD, None).unwrap();
+        assert!(Camera::framing(&world.bounding_box(), 1.5).is_some());
+
+        // Vertices alone leave nothing to look at.
+        let world = load_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\n", None).unwrap();
+        assert!(Camera::framing(&world.bounding_box(), 1.5).is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |obj: &str, mtl: Option<&str>| load_obj(obj, mtl).err().unwrap();
+
+        assert_eq!(
+            err("v 0 0 0\nv 1 0 0\nf 1 2 3", None),
+            ObjError {
+                file: ObjFile::Obj,
+                line: 3,
+                message: "position index 3 out of range (2 defined)".to_string(),
+            }
+        );
+        assert_eq!(err("v 0 0 x", None).message, "invalid number \"x\"");
+        assert_eq!(err("\n\nv 0 0", None).line, 3);
+        assert_eq!(err("v 0 0 0\nf 1 1", None).message, "face has 2 vertices");
+        assert_eq!(
+            err(QUAD, Some("newmtl matte\n")).message,
+            "unknown material \"glass\""
+        );
+
+        let mtl_err = err(QUAD, Some("newmtl matte\nKd 1 1\n"));
+        assert_eq!((mtl_err.file, mtl_err.line), (ObjFile::Mtl, 2));
+    }
+}
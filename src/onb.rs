@@ -0,0 +1,65 @@
+use crate::vec3::Vec3;
+
+// An orthonormal basis around `w`, for turning directions sampled around the
+// z axis into directions around a normal or towards a light.
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    // Duff et al.'s branchless construction, which stays continuous as `w`
+    // turns and has no axis it breaks down near.
+    pub fn new(w: Vec3) -> Self {
+        let w = w.unit();
+        let sign = 1.0f64.copysign(w.z());
+        let a = -1.0 / (sign + w.z());
+        let b = w.x() * w.y() * a;
+        Self {
+            u: Vec3::new(1.0 + sign * w.x() * w.x() * a, sign * b, -sign * w.x()),
+            v: Vec3::new(b, sign + w.y() * w.y() * a, -w.y()),
+            w,
+        }
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.w
+    }
+
+    // The direction with coordinates `a` in this basis.
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Onb;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_orthonormal() {
+        let normals = [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, -3.0),
+            Vec3::new(0.0, -1.0, 1e-9),
+        ];
+        for n in normals {
+            let onb = Onb::new(n);
+            let (u, v, w) = (onb.u, onb.v, onb.w);
+            for (a, b) in [(u, v), (v, w), (w, u)] {
+                assert!(a.dot(b).abs() < 1e-12);
+            }
+            for a in [u, v, w] {
+                assert!((a.length() - 1.0).abs() < 1e-12);
+            }
+            assert!((w - n.unit()).near_zero());
+            assert!((onb.local(Vec3::new(0.0, 0.0, 2.0)) - 2.0 * w).near_zero());
+            // Right-handed, so sampled hemispheres keep their orientation.
+            assert!((u.cross(v) - w).near_zero());
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Encoders for the images a `Renderer` produces: the display-ready RGBA bytes,
+// or for OpenEXR the linear colours before gamma and clamping.
+
+use std::fmt;
+
+use crate::vec3::Color;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodeError(String);
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not encode image: {}", self.0)
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+// Plain text PPM (P3), one pixel per line. The alpha channel is dropped.
+pub fn encode_ppm_ascii(width: u16, height: u16, rgba: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    check_len(width, height, rgba.len(), 4)?;
+
+    let mut out = format!("P3\n{width} {height}\n255\n");
+    for px in rgba.chunks_exact(4) {
+        out += &format!("{} {} {}\n", px[0], px[1], px[2]);
+    }
+    Ok(out.into_bytes())
+}
+
+// Binary PPM (P6). The alpha channel is dropped.
+pub fn encode_ppm(width: u16, height: u16, rgba: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    check_len(width, height, rgba.len(), 4)?;
+
+    let mut out = format!("P6\n{width} {height}\n255\n").into_bytes();
+    for px in rgba.chunks_exact(4) {
+        out.extend_from_slice(&px[..3]);
+    }
+    Ok(out)
+}
+
+// 8-bit RGBA PNG.
+pub fn encode_png(width: u16, height: u16, rgba: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    check_len(width, height, rgba.len(), 4)?;
+
+    let mut out = vec![];
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let fail = |e: png::EncodingError| EncodeError(e.to_string());
+    let mut writer = encoder.write_header().map_err(fail)?;
+    writer.write_image_data(rgba).map_err(fail)?;
+    writer.finish().map_err(fail)?;
+    Ok(out)
+}
+
+// Uncompressed scanline OpenEXR with 32-bit float R, G and B channels.
+pub fn encode_exr(width: u16, height: u16, pixels: &[Color]) -> Result<Vec<u8>, EncodeError> {
+    check_len(width, height, pixels.len(), 1)?;
+
+    let mut out = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+
+    // Channels are listed, and stored, in alphabetical order.
+    let mut channels = vec![];
+    for name in [b"B", b"G", b"R"] {
+        channels.extend_from_slice(name);
+        channels.push(0);
+        channels.extend(2i32.to_le_bytes()); // FLOAT
+        channels.extend([0, 0, 0, 0]); // pLinear and reserved
+        channels.extend(1i32.to_le_bytes()); // x sampling
+        channels.extend(1i32.to_le_bytes()); // y sampling
+    }
+    channels.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+
+    let attributes: [(&str, &str, Vec<u8>); 8] = [
+        ("channels", "chlist", channels),
+        ("compression", "compression", vec![0]),
+        ("dataWindow", "box2i", window.clone()),
+        ("displayWindow", "box2i", window),
+        ("lineOrder", "lineOrder", vec![0]),
+        ("pixelAspectRatio", "float", 1f32.to_le_bytes().to_vec()),
+        ("screenWindowCenter", "v2f", [0; 8].to_vec()),
+        ("screenWindowWidth", "float", 1f32.to_le_bytes().to_vec()),
+    ];
+    for (name, kind, value) in attributes {
+        out.extend(name.as_bytes());
+        out.push(0);
+        out.extend(kind.as_bytes());
+        out.push(0);
+        out.extend((value.len() as i32).to_le_bytes());
+        out.extend(value);
+    }
+    out.push(0);
+
+    // Each scanline is its own chunk: its row, its size, then the row of each
+    // channel in turn.
+    let line_size = width as usize * 3 * 4;
+    let first_line = out.len() + height as usize * 8;
+    for y in 0..height as usize {
+        out.extend(((first_line + y * (8 + line_size)) as u64).to_le_bytes());
+    }
+    for (y, row) in pixels.chunks_exact(width as usize).enumerate() {
+        out.extend((y as i32).to_le_bytes());
+        out.extend((line_size as i32).to_le_bytes());
+        for channel in [2, 1, 0] {
+            for px in row {
+                out.extend((px[channel] as f32).to_le_bytes());
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+// None of the formats can hold an image without pixels.
+fn check_len(width: u16, height: u16, len: usize, per_pixel: usize) -> Result<(), EncodeError> {
+    if width == 0 || height == 0 {
+        return Err(EncodeError(format!("a {width}x{height} image is empty")));
+    }
+    if len != width as usize * height as usize * per_pixel {
+        return Err(EncodeError(format!(
+            "buffer does not match a {width}x{height} image"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_exr, encode_png, encode_ppm, encode_ppm_ascii, EncodeError};
+    use crate::decode::decode_image;
+    use crate::vec3::Color;
+
+    const RGBA: [u8; 8] = [255, 0, 0, 255, 0, 0, 255, 255];
+
+    #[test]
+    fn test_ppm() {
+        let mut want = b"P6\n2 1\n255\n".to_vec();
+        want.extend([255, 0, 0, 0, 0, 255]);
+        assert_eq!(encode_ppm(2, 1, &RGBA).unwrap(), want);
+    }
+
+    #[test]
+    fn test_ppm_ascii() {
+        let want = "P3\n2 1\n255\n255 0 0\n0 0 255\n";
+        assert_eq!(encode_ppm_ascii(2, 1, &RGBA).unwrap(), want.as_bytes());
+    }
+
+    #[test]
+    fn test_exr_scanlines() {
+        let pixels = [Color::new(0.5, 2.0, 8.0), Color::new(-1.0, 0.0, 1.0)];
+        let exr = encode_exr(1, 2, &pixels).unwrap();
+        assert_eq!(exr[..4], [0x76, 0x2f, 0x31, 0x01]);
+
+        // Between the header and the two 20 byte scanlines is a table of where
+        // each scanline starts.
+        let table = exr.len() - 2 * 20 - 2 * 8;
+        let read_u64 = |at: usize| u64::from_le_bytes(exr[at..at + 8].try_into().unwrap());
+        let read_f32 = |at: usize| f32::from_le_bytes(exr[at..at + 4].try_into().unwrap());
+        for (y, px) in pixels.iter().enumerate() {
+            let line = read_u64(table + 8 * y) as usize;
+            assert_eq!(exr[line..line + 4], (y as i32).to_le_bytes());
+            assert_eq!(exr[line + 4..line + 8], 12i32.to_le_bytes());
+            let bgr = [read_f32(line + 8), read_f32(line + 12), read_f32(line + 16)];
+            assert_eq!(bgr, [px.z() as f32, px.y() as f32, px.x() as f32]);
+        }
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let img = decode_image(&encode_png(1, 2, &RGBA).unwrap()).unwrap();
+        assert_eq!((img.width(), img.height()), (1, 2));
+        assert_eq!(img.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(img.pixel(0, 1), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_errors() {
+        let empty = Err(EncodeError("a 0x2 image is empty".into()));
+        assert_eq!(encode_png(0, 2, &[]), empty);
+        assert_eq!(encode_ppm(0, 2, &[]), empty);
+        assert_eq!(encode_ppm_ascii(0, 2, &[]), empty);
+        assert_eq!(encode_exr(0, 2, &[]), empty);
+
+        let short = Err(EncodeError("buffer does not match a 2x2 image".into()));
+        assert_eq!(encode_png(2, 2, &RGBA), short);
+        assert_eq!(encode_exr(2, 2, &[Color::new(0.0, 0.0, 0.0)]), short);
+    }
+}
@@ -0,0 +1,214 @@
+use std::f64::consts::PI;
+
+use crate::environment::Environment;
+use crate::hittable::Hittable;
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+// A way of picking directions, with the density per solid angle it picks
+// each one with, so that strategies can be mixed and weighted against each
+// other.
+pub trait Pdf {
+    fn value(&self, dir: Vec3) -> f64;
+
+    // A unit direction, or `None` if the strategy found nothing to aim at.
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3>;
+}
+
+// Directions over the hemisphere around a normal, in proportion to their
+// cosine with it.
+pub struct CosinePdf {
+    onb: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: Vec3) -> Self {
+        Self {
+            onb: Onb::new(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, dir: Vec3) -> f64 {
+        let cosine = dir.unit().dot(self.onb.w());
+        cosine.max(0.0) / PI
+    }
+
+    // Malley's method: points spread evenly over the disk, lifted up onto
+    // the hemisphere.
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        let (u1, u2) = sampler.get_2d();
+        let (r, phi) = (u1.sqrt(), 2.0 * PI * u2);
+        let z = (1.0 - u1).max(0.0).sqrt();
+        Some(self.onb.local(Vec3::new(r * phi.cos(), r * phi.sin(), z)))
+    }
+}
+
+// Directions from `origin` towards points on an object.
+pub struct HittablePdf<'a> {
+    object: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(object: &'a dyn Hittable, origin: Point3) -> Self {
+        Self { object, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, dir: Vec3) -> f64 {
+        self.object.pdf(self.origin, dir)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        self.object.sample(self.origin, sampler.get_2d())
+    }
+}
+
+// Directions towards the bright parts of the environment.
+pub struct EnvironmentPdf<'a> {
+    env: &'a dyn Environment,
+}
+
+impl<'a> EnvironmentPdf<'a> {
+    pub fn new(env: &'a dyn Environment) -> Self {
+        Self { env }
+    }
+}
+
+impl Pdf for EnvironmentPdf<'_> {
+    fn value(&self, dir: Vec3) -> f64 {
+        self.env.pdf(dir)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        self.env.sample(sampler.get_2d()).map(|s| s.dir)
+    }
+}
+
+// Picks from `a` with probability `weight` and from `b` otherwise.
+pub struct MixturePdf<'a> {
+    a: &'a dyn Pdf,
+    b: &'a dyn Pdf,
+    weight: f64,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(a: &'a dyn Pdf, b: &'a dyn Pdf, weight: f64) -> Self {
+        Self { a, b, weight }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, dir: Vec3) -> f64 {
+        self.weight * self.a.value(dir) + (1.0 - self.weight) * self.b.value(dir)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        if sampler.get_1d() < self.weight {
+            self.a.generate(sampler)
+        } else {
+            self.b.generate(sampler)
+        }
+    }
+}
+
+// The strategy a material's `scatter` picks directions with. The cosine
+// distribution most diffuse surfaces use is held by value, so bouncing off
+// them doesn't allocate; any other strategy is boxed.
+pub enum ScatterPdf {
+    Cosine(CosinePdf),
+    Other(Box<dyn Pdf>),
+}
+
+impl Pdf for ScatterPdf {
+    fn value(&self, dir: Vec3) -> f64 {
+        match self {
+            Self::Cosine(pdf) => pdf.value(dir),
+            Self::Other(pdf) => pdf.value(dir),
+        }
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        match self {
+            Self::Cosine(pdf) => pdf.generate(sampler),
+            Self::Other(pdf) => pdf.generate(sampler),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::{CosinePdf, HittablePdf, MixturePdf, Pdf, ScatterPdf};
+    use crate::material::DiffuseLight;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    // Sampled directions get the density the strategy reports for them, and
+    // that density integrates to one over the sphere of directions.
+    fn check(pdf: &dyn Pdf) {
+        let mut sampler = IndependentSampler::new(0);
+        let n = 200_000;
+        let mut integral = 0.0;
+        for _ in 0..n {
+            let dir = pdf.generate(&mut sampler).unwrap();
+            assert!((dir.length() - 1.0).abs() < 1e-9);
+            assert!(pdf.value(dir) > 0.0);
+            integral += 4.0 * PI * pdf.value(Vec3::random_unit_vector(&mut sampler));
+        }
+        let integral = integral / n as f64;
+        assert!((integral - 1.0).abs() < 0.03, "integral was {integral}");
+    }
+
+    #[test]
+    fn test_cosine() {
+        let n = Vec3::new(1.0, 1.0, 0.0);
+        let cosine = CosinePdf::new(n);
+        check(&cosine);
+        assert!((cosine.value(n) - 1.0 / PI).abs() < 1e-12);
+        assert_eq!(cosine.value(-n), 0.0);
+
+        // Half the directions are within 45 degrees of the normal.
+        let mut sampler = IndependentSampler::new(1);
+        let near = (0..10_000)
+            .filter(|_| cosine.generate(&mut sampler).unwrap().dot(n.unit()) > 0.5f64.sqrt())
+            .count();
+        assert!((near as f64 / 10_000.0 - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_mixture() {
+        let light = Sphere::new(
+            Point3::new(0.0, 3.0, 0.0),
+            1.0,
+            DiffuseLight::new(Color::new(1.0, 1.0, 1.0)),
+        );
+        let towards = HittablePdf::new(&light, Point3::default());
+        let cosine = CosinePdf::new(Vec3::new(0.0, 0.0, 1.0));
+        check(&towards);
+        let mixture = MixturePdf::new(&towards, &cosine, 0.25);
+        check(&mixture);
+
+        let dir = Vec3::new(0.0, 1.0, 0.0);
+        let want = 0.25 * towards.value(dir) + 0.75 * cosine.value(dir);
+        assert_eq!(mixture.value(dir), want);
+    }
+
+    #[test]
+    fn test_scatter() {
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        for pdf in [
+            ScatterPdf::Cosine(CosinePdf::new(n)),
+            ScatterPdf::Other(Box::new(CosinePdf::new(n))),
+        ] {
+            check(&pdf);
+            assert!((pdf.value(n) - 1.0 / PI).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::vec3::{Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+// Ken Perlin's gradient noise, with random unit gradients on the lattice.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let g = Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                if g.length_squared() < 1.0 && !g.near_zero() {
+                    break g.unit();
+                }
+            })
+            .collect();
+
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(rng);
+            p
+        };
+
+        Self {
+            gradients,
+            perm: [permutation(), permutation(), permutation()],
+        }
+    }
+
+    // Smooth noise in about [-1, 1].
+    pub fn noise(&self, p: Point3) -> f64 {
+        let cell = [p.x().floor(), p.y().floor(), p.z().floor()];
+        let frac = [p.x() - cell[0], p.y() - cell[1], p.z() - cell[2]];
+        // Hermite smoothing hides the lattice.
+        let smooth = frac.map(|f| f * f * (3.0 - 2.0 * f));
+
+        let mut accum = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let index = (0..3)
+                .map(|axis| {
+                    let lattice = (cell[axis] as i64 + offset[axis] as i64) as usize;
+                    self.perm[axis][lattice & (POINT_COUNT - 1)]
+                })
+                .fold(0, |acc, i| acc ^ i);
+
+            let weight = Vec3::new(
+                frac[0] - offset[0] as f64,
+                frac[1] - offset[1] as f64,
+                frac[2] - offset[2] as f64,
+            );
+            let blend: f64 = (0..3)
+                .map(|axis| match offset[axis] {
+                    0 => 1.0 - smooth[axis],
+                    _ => smooth[axis],
+                })
+                .product();
+
+            accum += blend * self.gradients[index].dot(weight);
+        }
+        accum
+    }
+
+    // The sum of `depth` octaves of absolute noise, each at double the
+    // frequency and half the weight of the last.
+    pub fn turbulence(&self, p: Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(p);
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+        accum.abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::Perlin;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_noise() {
+        let perlin = Perlin::new(&mut SmallRng::seed_from_u64(7));
+
+        // Noise vanishes on the lattice and is continuous between points.
+        assert_eq!(perlin.noise(Point3::new(3.0, -2.0, 5.0)), 0.0);
+        let p = Point3::new(0.3, 1.7, -2.2);
+        let q = Point3::new(0.3001, 1.7, -2.2);
+        assert!((perlin.noise(p) - perlin.noise(q)).abs() < 1e-3);
+
+        for i in 0..100 {
+            let p = Point3::new(i as f64 * 0.37, i as f64 * -0.11, i as f64 * 0.73);
+            assert!(perlin.noise(p).abs() <= 1.0);
+            assert!(perlin.turbulence(p, 7) >= 0.0);
+        }
+    }
+}
@@ -0,0 +1,868 @@
+use js_sys::Uint8ClampedArray;
+use rand::{rngs::SmallRng, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::camera::Camera;
+use crate::environment::{Environment, GradientEnvironment};
+use crate::film::{Adaptive, Film, SampleGrid};
+use crate::filter::{Filter, FilterKind};
+use crate::flat_bvh::FlatBvh;
+use crate::hittable::{Hittable, HittableList};
+use crate::integrator::{
+    AmbientOcclusionIntegrator, DebugIntegrator, DebugView, Integrator, IntegratorKind,
+    PathIntegrator, WhittedIntegrator,
+};
+use crate::light::LightList;
+use crate::obj::load_obj;
+use crate::output;
+use crate::sampler::{
+    mix, AnySampler, HaltonSampler, IndependentSampler, Sampler, SamplerKind, SobolSampler,
+    StratifiedSampler,
+};
+use crate::scene::{RenderSettings, Scene};
+use crate::tonemap::{ToneMap, ToneMapping};
+use crate::utils;
+use crate::vec3::{Color, Point3, Vec3};
+
+fn js_error(e: impl std::fmt::Display) -> JsValue {
+    JsValue::from(format!("{e}"))
+}
+
+// Keeps a scene between frames and sums every sample taken of each pixel, so
+// the image can be refined a few samples at a time. Pixels are counted
+// separately, so workers can each refine their own tiles of the same scene.
+//
+// Every sample draws from its own sampler, seeded by the renderer's seed,
+// the pixel and how many samples the pixel already has. So a seed gives the
+// same image however the work is split into passes and tiles.
+#[wasm_bindgen]
+pub struct Renderer {
+    world: FlatBvh,
+    lights: LightList,
+    camera: Camera,
+    environment: Box<dyn Environment>,
+    max_depth: u16,
+    min_bounces: u16,
+    integrator: IntegratorKind,
+    // How far ambient occlusion looks for surfaces in the way.
+    ao_distance: f64,
+    film: Film,
+    tone_mapping: ToneMapping,
+    sampler_kind: SamplerKind,
+    adaptive: Option<Adaptive>,
+    // How many samples stratified sampling spreads each pixel's strata over.
+    samples_per_pixel: u32,
+    seed: u64,
+}
+
+impl Renderer {
+    pub fn new(
+        world: HittableList,
+        camera: Camera,
+        environment: Box<dyn Environment>,
+        max_depth: u16,
+        width: u16,
+        height: u16,
+        seed: u64,
+    ) -> Self {
+        Self {
+            lights: world.lights().clone(),
+            world: FlatBvh::new(world),
+            camera,
+            environment,
+            max_depth,
+            min_bounces: RenderSettings::default().min_bounces,
+            integrator: IntegratorKind::Path,
+            ao_distance: 1.0,
+            film: Film::new(width, height, Filter::default()),
+            tone_mapping: ToneMapping::default(),
+            sampler_kind: SamplerKind::Independent,
+            adaptive: None,
+            samples_per_pixel: RenderSettings::default().samples_per_pixel,
+            seed,
+        }
+    }
+
+    pub fn with_scene(scene: Scene, width: u16, height: u16, seed: u64) -> Self {
+        let camera = scene.camera.build(width as f64 / height as f64);
+        let mut renderer = Self::new(
+            scene.world,
+            camera,
+            scene.environment,
+            scene.settings.max_depth,
+            width,
+            height,
+            seed,
+        );
+        renderer.set_filter(scene.settings.filter);
+        renderer.set_sampler(scene.settings.sampler, scene.settings.samples_per_pixel);
+        renderer.set_adaptive(scene.settings.adaptive);
+        renderer.min_bounces = scene.settings.min_bounces;
+        renderer.tone_mapping = scene.settings.tone_mapping;
+        renderer
+    }
+
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.film.filter()
+    }
+
+    // Changes how samples are weighted into pixels. This throws away the
+    // samples taken so far.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.film = Film::new(self.width(), self.height(), filter);
+    }
+
+    pub fn sampler_kind(&self) -> SamplerKind {
+        self.sampler_kind
+    }
+
+    // Changes where samples are taken, for stratified sampling spreading
+    // `samples_per_pixel` samples evenly. This throws away the samples taken
+    // so far.
+    pub fn set_sampler(&mut self, kind: SamplerKind, samples_per_pixel: u32) {
+        self.sampler_kind = kind;
+        self.samples_per_pixel = samples_per_pixel.max(1);
+        self.film.clear();
+    }
+
+    pub fn integrator_kind(&self) -> IntegratorKind {
+        self.integrator
+    }
+
+    // Changes how camera rays are traced. Switching to another kind throws
+    // away the samples taken so far.
+    pub fn set_integrator(&mut self, kind: IntegratorKind) {
+        if kind != self.integrator {
+            self.integrator = kind;
+            self.film.clear();
+        }
+    }
+
+    // How far ambient occlusion looks, which must be positive. Changing it
+    // starts an ambient occlusion image again.
+    pub fn set_ao_distance(&mut self, distance: f64) {
+        self.ao_distance = distance;
+        if self.integrator == IntegratorKind::AmbientOcclusion {
+            self.film.clear();
+        }
+    }
+
+    fn integrator(&self) -> Box<dyn Integrator> {
+        let debug = |view| Box::new(DebugIntegrator::new(view));
+        match self.integrator {
+            IntegratorKind::Path => Box::new(PathIntegrator::new(self.max_depth, self.min_bounces)),
+            IntegratorKind::Whitted => Box::new(WhittedIntegrator::new(self.max_depth)),
+            IntegratorKind::AmbientOcclusion => {
+                Box::new(AmbientOcclusionIntegrator::new(self.ao_distance))
+            }
+            IntegratorKind::Normals => debug(DebugView::Normals),
+            IntegratorKind::Depth => debug(DebugView::Depth),
+            IntegratorKind::Uv => debug(DebugView::Uv),
+            IntegratorKind::MaterialId => debug(DebugView::MaterialId),
+            IntegratorKind::Albedo => debug(DebugView::Albedo),
+        }
+    }
+
+    pub fn adaptive(&self) -> Option<Adaptive> {
+        self.adaptive
+    }
+
+    // With `Some`, passes only sample the pixels that haven't yet converged.
+    pub fn set_adaptive(&mut self, adaptive: Option<Adaptive>) {
+        self.adaptive = adaptive;
+    }
+
+    // Changes how the image is shown, without throwing away any samples.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    // Adds `samples` more samples to every pixel.
+    pub fn accumulate(&mut self, samples: u32) {
+        self.accumulate_tile(0, 0, self.width(), self.height(), samples);
+    }
+
+    // Adds `samples` more samples to each pixel of the `w` by `h` rectangle
+    // whose top left corner is pixel (x, y). In adaptive mode, pixels that
+    // have converged are skipped. With the `parallel` feature the rows are
+    // traced on rayon's thread pool.
+    //
+    // A pixel's nth sample is the filtered sum of the nth samples of the
+    // pixels its filter reaches, so those beyond the tile are traced too.
+    pub fn accumulate_tile(&mut self, x: u16, y: u16, w: u16, h: u16, samples: u32) {
+        let m = self.film.margin();
+        let (x0, y0) = (x.saturating_sub(m), y.saturating_sub(m));
+        let x1 = x.saturating_add(w).saturating_add(m).min(self.width());
+        let y1 = y.saturating_add(h).saturating_add(m).min(self.height());
+
+        let tile: Vec<_> = (y..y + h)
+            .flat_map(|row| (x..x + w).map(move |col| (col, row)))
+            .collect();
+        let wanted: Vec<u32> = tile
+            .iter()
+            .map(|&(col, row)| {
+                let index = self.film.index(col, row);
+                self.film.wanted(index, samples, self.adaptive.as_ref())
+            })
+            .collect();
+        let integrator = self.integrator();
+
+        for i in 0..samples {
+            // Pixels may have had different numbers of samples, so each
+            // sample number is traced separately.
+            let mut taken: Vec<u32> = tile
+                .iter()
+                .zip(&wanted)
+                .filter(|(_, &wanted)| i < wanted)
+                .map(|(&(col, row), _)| self.film.count(self.film.index(col, row)))
+                .collect();
+            taken.sort_unstable();
+            taken.dedup();
+
+            for sample in taken {
+                let sampled = |col: u16, row: u16| {
+                    (x..x + w).contains(&col)
+                        && (y..y + h).contains(&row)
+                        && i < wanted[(row - y) as usize * w as usize + (col - x) as usize]
+                        && self.film.count(self.film.index(col, row)) == sample
+                };
+                // Whether the filter of a pixel being sampled reaches (col, row).
+                let needed = |col: u16, row: u16| {
+                    (row.saturating_sub(m)..=row.saturating_add(m)).any(|r| {
+                        (col.saturating_sub(m)..=col.saturating_add(m)).any(|c| sampled(c, r))
+                    })
+                };
+                let trace_row = |row: u16| {
+                    (x0..x1)
+                        .map(|col| {
+                            needed(col, row)
+                                .then(|| self.trace(integrator.as_ref(), col, row, sample))
+                        })
+                        .collect::<Vec<_>>()
+                };
+                #[cfg(feature = "parallel")]
+                let rows: Vec<_> = (y0..y1).into_par_iter().map(trace_row).collect();
+                #[cfg(not(feature = "parallel"))]
+                let rows: Vec<_> = (y0..y1).map(trace_row).collect();
+                let grid = SampleGrid {
+                    x: x0,
+                    y: y0,
+                    w: x1 - x0,
+                    samples: rows.concat(),
+                };
+
+                let gathered: Vec<_> = tile
+                    .iter()
+                    .filter(|&&(col, row)| sampled(col, row))
+                    .map(|&(col, row)| {
+                        let index = self.film.index(col, row);
+                        (index, self.film.gather(col, row, &grid))
+                    })
+                    .collect();
+                for (index, sample) in gathered {
+                    self.film.add(index, sample);
+                }
+            }
+        }
+    }
+
+    // Pixel (col, row)'s sample number `sample`: where in the pixel it landed
+    // and the radiance it carried.
+    fn trace(
+        &self,
+        integrator: &dyn Integrator,
+        col: u16,
+        row: u16,
+        sample: u32,
+    ) -> ((f64, f64), Color) {
+        let (width, height) = (self.width() as f64, self.height() as f64);
+        // Rows count down from the top of the view.
+        let (i, j) = (col as f64, (self.height() - 1 - row) as f64);
+
+        let sampler = &mut self.sampler(self.film.index(col, row), sample);
+        let (du, dv) = sampler.get_2d();
+        let (u, v) = ((i + du) / (width - 1.0), (j + dv) / (height - 1.0));
+        let r = self.camera.ray(u, v, sampler);
+        let c = integrator.radiance(
+            &r,
+            &self.world,
+            &self.lights,
+            self.environment.as_ref(),
+            sampler,
+        );
+        ((du, 1.0 - dv), c)
+    }
+
+    // The sampler for pixel `index`'s sample number `sample`. Independent
+    // samples are each seeded separately; the others are seeded once per
+    // pixel and given the sample number.
+    fn sampler(&self, index: usize, sample: u32) -> AnySampler {
+        let seed = |sample: u32| {
+            let key = (index as u64) << 32 | sample as u64;
+            mix(self.seed.wrapping_add(mix(key)))
+        };
+        match self.sampler_kind {
+            SamplerKind::Independent => {
+                AnySampler::Independent(IndependentSampler::new(seed(sample)))
+            }
+            SamplerKind::Stratified => AnySampler::Stratified(StratifiedSampler::new(
+                seed(0),
+                sample,
+                self.samples_per_pixel,
+            )),
+            SamplerKind::Halton => AnySampler::Halton(HaltonSampler::new(seed(0), sample)),
+            SamplerKind::Sobol => AnySampler::Sobol(SobolSampler::new(seed(0), sample)),
+        }
+    }
+
+    // The average of the samples so far, tone mapped to sRGB RGBA bytes.
+    pub fn image(&self) -> Vec<u8> {
+        self.tile_image(0, 0, self.width(), self.height())
+    }
+
+    pub fn tile_image(&self, x: u16, y: u16, w: u16, h: u16) -> Vec<u8> {
+        (y..y + h)
+            .flat_map(|row| (x..x + w).map(move |col| self.film.index(col, row)))
+            .flat_map(|index| self.tone_mapping.rgba(self.film.pixel(index)))
+            .collect()
+    }
+
+    // The average of the samples so far, before tone mapping.
+    pub fn linear(&self) -> Vec<Color> {
+        let len = self.width() as usize * self.height() as usize;
+        (0..len).map(|index| self.film.pixel(index)).collect()
+    }
+
+    // The most samples taken of any pixel.
+    pub fn most_samples(&self) -> u32 {
+        self.film.most_samples()
+    }
+
+    // How many samples each pixel has taken, as RGBA bytes running from black
+    // for none through red and yellow to white for the most: `max_samples` in
+    // adaptive mode, otherwise the most any pixel has.
+    pub fn heatmap(&self) -> Vec<u8> {
+        self.tile_heatmap(0, 0, self.width(), self.height())
+    }
+
+    pub fn tile_heatmap(&self, x: u16, y: u16, w: u16, h: u16) -> Vec<u8> {
+        let most = match self.adaptive {
+            Some(adaptive) => adaptive.max_samples,
+            None => self.film.most_samples(),
+        };
+        (y..y + h)
+            .flat_map(|row| (x..x + w).map(move |col| self.film.index(col, row)))
+            .flat_map(|index| {
+                let t = self.film.count(index) as f64 / most.max(1) as f64;
+                let channel = |offset: f64| (255.0 * (3.0 * t - offset).clamp(0.0, 1.0)) as u8;
+                [channel(0.0), channel(1.0), channel(2.0), 255]
+            })
+            .collect()
+    }
+
+    fn contains_tile(&self, x: u16, y: u16, w: u16, h: u16) -> bool {
+        x as u32 + w as u32 <= self.width() as u32 && y as u32 + h as u32 <= self.height() as u32
+    }
+
+    fn check_tile(&self, x: u16, y: u16, w: u16, h: u16) -> Result<(), JsValue> {
+        if !self.contains_tile(x, y, w, h) {
+            return Err(JsValue::from(format!(
+                "tile {w}x{h} at ({x}, {y}) is outside the {}x{} image",
+                self.width(),
+                self.height()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl Renderer {
+    // The scene of random spheres from the cover of "Ray Tracing in One
+    // Weekend". Renderers given the same seed render the same image.
+    pub fn random(width: u16, height: u16, seed: u32) -> Renderer {
+        utils::set_panic_hook();
+
+        let world = HittableList::random_scene(&mut SmallRng::seed_from_u64(seed as u64));
+        let camera = Camera::new(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            20.0,
+            width as f64 / height as f64,
+            0.1,
+            10.0,
+        );
+
+        Self::new(
+            world,
+            camera,
+            Box::new(GradientEnvironment::default()),
+            RenderSettings::default().max_depth,
+            width,
+            height,
+            seed as u64,
+        )
+    }
+
+    // A scene in the JSON format read by `Scene::from_json`.
+    pub fn from_scene(json: &str, width: u16, height: u16, seed: u32) -> Result<Renderer, JsValue> {
+        utils::set_panic_hook();
+
+        let scene = Scene::from_json(json).map_err(js_error)?;
+        Ok(Self::with_scene(scene, width, height, seed as u64))
+    }
+
+    // An OBJ model, given as a string or `Uint8Array`, with the MTL library it
+    // uses if there is one. The camera is placed to frame the model.
+    pub fn from_obj(
+        obj: JsValue,
+        mtl: JsValue,
+        width: u16,
+        height: u16,
+        seed: u32,
+    ) -> Result<Renderer, JsValue> {
+        utils::set_panic_hook();
+
+        let obj = utils::text_from_js(&obj)?;
+        let mtl = if mtl.is_undefined() || mtl.is_null() {
+            None
+        } else {
+            Some(utils::text_from_js(&mtl)?)
+        };
+        let world = load_obj(&obj, mtl.as_deref()).map_err(js_error)?;
+        let camera = Camera::framing(&world.bounding_box(), width as f64 / height as f64)
+            .ok_or_else(|| JsValue::from("no faces to frame"))?;
+
+        Ok(Self::new(
+            world,
+            camera,
+            Box::new(GradientEnvironment::default()),
+            RenderSettings::default().max_depth,
+            width,
+            height,
+            seed as u64,
+        ))
+    }
+
+    // Adds `samples` samples per pixel and returns the refined image. Given an
+    // `integrator` other than the last one used, starts the image again with
+    // that one.
+    pub fn render_pass(
+        &mut self,
+        samples: u32,
+        integrator: Option<IntegratorKind>,
+    ) -> Uint8ClampedArray {
+        if let Some(kind) = integrator {
+            self.set_integrator(kind);
+        }
+        self.accumulate(samples);
+        self.image()[..].into()
+    }
+
+    // Adds `samples` samples per pixel to one tile, as for `accumulate_tile`,
+    // and returns just that tile's pixels. `integrator` is as for
+    // `render_pass`.
+    pub fn render_tile(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        samples: u32,
+        integrator: Option<IntegratorKind>,
+    ) -> Result<Uint8ClampedArray, JsValue> {
+        self.check_tile(x, y, w, h)?;
+        if let Some(kind) = integrator {
+            self.set_integrator(kind);
+        }
+        self.accumulate_tile(x, y, w, h, samples);
+        Ok(self.tile_image(x, y, w, h)[..].into())
+    }
+
+    // The current image as a file, for the page to offer as a download.
+    pub fn to_png(&self) -> Result<Vec<u8>, JsValue> {
+        output::encode_png(self.width(), self.height(), &self.image()).map_err(js_error)
+    }
+
+    pub fn to_ppm(&self, ascii: bool) -> Result<Vec<u8>, JsValue> {
+        let ppm = if ascii {
+            output::encode_ppm_ascii(self.width(), self.height(), &self.image())
+        } else {
+            output::encode_ppm(self.width(), self.height(), &self.image())
+        };
+        ppm.map_err(js_error)
+    }
+
+    pub fn to_exr(&self) -> Result<Vec<u8>, JsValue> {
+        output::encode_exr(self.width(), self.height(), &self.linear()).map_err(js_error)
+    }
+
+    // The samples taken of each pixel as a heatmap, as for `heatmap`.
+    pub fn sample_heatmap(&self) -> Uint8ClampedArray {
+        self.heatmap()[..].into()
+    }
+
+    pub fn tile_sample_heatmap(
+        &self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+    ) -> Result<Uint8ClampedArray, JsValue> {
+        self.check_tile(x, y, w, h)?;
+        Ok(self.tile_heatmap(x, y, w, h)[..].into())
+    }
+
+    // From now on, only samples pixels whose luminance has a standard error
+    // of more than `threshold` of it, taking between `min_samples` and
+    // `max_samples` samples of each.
+    pub fn use_adaptive(
+        &mut self,
+        threshold: f64,
+        min_samples: u32,
+        max_samples: u32,
+    ) -> Result<(), JsValue> {
+        let adaptive = Adaptive {
+            threshold,
+            min_samples,
+            max_samples,
+        };
+        adaptive
+            .check()
+            .map_err(|(field, message)| JsValue::from(format!("{field} {message}")))?;
+        self.set_adaptive(Some(adaptive));
+        Ok(())
+    }
+
+    // From now on, samples every pixel in every pass.
+    pub fn use_uniform_sampling(&mut self) {
+        self.set_adaptive(None);
+    }
+
+    // How many pixels the next pass would sample: all of them, unless in
+    // adaptive mode.
+    #[wasm_bindgen(getter)]
+    pub fn active_pixels(&self) -> usize {
+        (0..self.film.width() as usize * self.film.height() as usize)
+            .filter(|&index| self.film.wanted(index, 1, self.adaptive.as_ref()) > 0)
+            .count()
+    }
+
+    // How many times every path bounces before Russian roulette may end it.
+    // Roulette doesn't bias the image, so changing this keeps the samples
+    // taken so far.
+    #[wasm_bindgen(getter)]
+    pub fn min_bounces(&self) -> u16 {
+        self.min_bounces
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_min_bounces(&mut self, min_bounces: u16) {
+        self.min_bounces = min_bounces;
+    }
+
+    // How far ambient occlusion looks for surfaces that shade a point, in
+    // scene units.
+    #[wasm_bindgen(getter)]
+    pub fn ao_distance(&self) -> f64 {
+        self.ao_distance
+    }
+
+    // Changes `ao_distance`, starting an ambient occlusion image again.
+    pub fn use_ao_distance(&mut self, distance: f64) -> Result<(), JsValue> {
+        if distance.is_nan() || distance <= 0.0 {
+            return Err(JsValue::from(format!(
+                "ambient occlusion distance must be positive, got {distance}"
+            )));
+        }
+        self.set_ao_distance(distance);
+        Ok(())
+    }
+
+    // Throws away the samples taken so far.
+    pub fn reset(&mut self) {
+        self.film.clear();
+    }
+
+    // The fewest samples taken of any pixel.
+    #[wasm_bindgen(getter)]
+    pub fn samples(&self) -> u32 {
+        self.film.samples()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u16 {
+        self.film.width()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u16 {
+        self.film.height()
+    }
+
+    // How the image is tone mapped. Changing it takes effect on the next
+    // image returned, without throwing away any samples.
+    #[wasm_bindgen(getter)]
+    pub fn tone_map(&self) -> ToneMap {
+        self.tone_mapping.operator
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_tone_map(&mut self, operator: ToneMap) {
+        self.tone_mapping.operator = operator;
+    }
+
+    // In stops, so each one doubles the brightness.
+    #[wasm_bindgen(getter)]
+    pub fn exposure(&self) -> f64 {
+        self.tone_mapping.exposure
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_exposure(&mut self, exposure: f64) {
+        self.tone_mapping.exposure = exposure;
+    }
+
+    // The luminance shown as white by the extended Reinhard operator.
+    #[wasm_bindgen(getter)]
+    pub fn white(&self) -> f64 {
+        self.tone_mapping.white
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_white(&mut self, white: f64) {
+        self.tone_mapping.white = white;
+    }
+
+    // Weights samples into pixels with a `kind` filter reaching `radius`
+    // pixels from their centres, starting the image again.
+    pub fn use_filter(&mut self, kind: FilterKind, radius: f64) -> Result<(), JsValue> {
+        if !(radius > 0.0 && radius.is_finite()) {
+            return Err(JsValue::from(format!(
+                "filter radius must be positive, got {radius}"
+            )));
+        }
+        self.set_filter(Filter { kind, radius });
+        Ok(())
+    }
+
+    // Takes samples with a `kind` sampler, starting the image again. Stratified
+    // sampling spreads each `samples_per_pixel` samples of a pixel evenly.
+    pub fn use_sampler(
+        &mut self,
+        kind: SamplerKind,
+        samples_per_pixel: u32,
+    ) -> Result<(), JsValue> {
+        if samples_per_pixel == 0 {
+            return Err(JsValue::from("samples_per_pixel must be at least 1"));
+        }
+        self.set_sampler(kind, samples_per_pixel);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::Renderer;
+    use crate::camera::CameraSettings;
+    use crate::environment::SolidEnvironment;
+    use crate::film::Adaptive;
+    use crate::filter::{Filter, FilterKind};
+    use crate::hittable::{Hittable, HittableList};
+    use crate::integrator::IntegratorKind;
+    use crate::ray::Ray;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    #[test]
+    fn test_accumulate() {
+        let camera = CameraSettings {
+            lookfrom: Point3::new(0.0, 0.0, 0.0),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+        }
+        .build(2.0);
+        let env = SolidEnvironment::new(Color::new(0.25, 0.25, 0.25));
+        let mut renderer = Renderer::new(HittableList::new(), camera, Box::new(env), 10, 4, 2, 0);
+
+        // Nothing has been rendered yet.
+        assert_eq!(renderer.image(), [0, 0, 0, 255].repeat(8));
+
+        renderer.accumulate(3);
+        renderer.accumulate(2);
+        assert_eq!(renderer.samples(), 5);
+        // 0.25 in sRGB.
+        assert_eq!(renderer.image(), [137, 137, 137, 255].repeat(8));
+
+        renderer.reset();
+        assert_eq!(renderer.samples(), 0);
+        assert_eq!(renderer.image(), [0, 0, 0, 255].repeat(8));
+
+        // Only the tile's pixels are sampled.
+        renderer.accumulate_tile(1, 1, 2, 1, 1);
+        assert_eq!(renderer.samples(), 0);
+        assert_eq!(
+            renderer.tile_image(1, 1, 2, 1),
+            [137, 137, 137, 255].repeat(2)
+        );
+        assert_eq!(renderer.tile_image(0, 1, 1, 1), [0, 0, 0, 255]);
+        assert!(renderer.contains_tile(2, 0, 2, 2));
+        assert!(!renderer.contains_tile(3, 0, 2, 2));
+    }
+
+    #[test]
+    fn test_seeded_scene() {
+        let scene = |seed| HittableList::random_scene(&mut SmallRng::seed_from_u64(seed));
+        let (a, b, c) = (scene(1), scene(1), scene(2));
+
+        // Heights seen looking straight down on a grid over the small spheres.
+        let t = |world: &HittableList| {
+            (-10..10)
+                .flat_map(|x| (-10..10).map(move |z| (x, z)))
+                .map(|(x, z)| {
+                    let org = Point3::new(x as f64 + 0.5, 5.0, z as f64 + 0.5);
+                    let r = Ray::new(org, Vec3::new(0.0, -1.0, 0.0));
+                    world.hit(&r, 0.001, f64::INFINITY).map(|i| i.t)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(a.bounding_box(), b.bounding_box());
+        assert_eq!(t(&a), t(&b));
+        assert_ne!(t(&a), t(&c));
+    }
+
+    #[test]
+    fn test_seed_reproduces_image() {
+        let mut whole = Renderer::random(16, 9, 7);
+        whole.accumulate(3);
+
+        // The same samples, split into passes and tiles taken out of order.
+        let mut tiled = Renderer::random(16, 9, 7);
+        for _ in 0..3 {
+            tiled.accumulate_tile(8, 0, 8, 9, 1);
+            tiled.accumulate_tile(0, 0, 8, 9, 1);
+        }
+        assert_eq!(whole.image(), tiled.image());
+
+        let mut other = Renderer::random(16, 9, 8);
+        other.accumulate(3);
+        assert_ne!(whole.image(), other.image());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_serial() {
+        let mut parallel = Renderer::random(16, 9, 7);
+        parallel.accumulate(3);
+
+        // Row by row on a single thread.
+        let mut serial = Renderer::random(16, 9, 7);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            for row in 0..9 {
+                serial.accumulate_tile(0, row, 16, 1, 3);
+            }
+        });
+        assert_eq!(parallel.linear(), serial.linear());
+    }
+
+    #[test]
+    fn test_adaptive() {
+        let adaptive = Adaptive {
+            threshold: 0.05,
+            min_samples: 4,
+            max_samples: 32,
+        };
+        let mut whole = Renderer::random(16, 9, 7);
+        whole.set_adaptive(Some(adaptive));
+        let mut tiled = Renderer::random(16, 9, 7);
+        tiled.set_adaptive(Some(adaptive));
+        while whole.active_pixels() > 0 {
+            whole.accumulate(4);
+            tiled.accumulate_tile(0, 0, 16, 5, 4);
+            tiled.accumulate_tile(0, 5, 16, 4, 4);
+        }
+        assert_eq!(whole.linear(), tiled.linear());
+        assert_eq!(tiled.active_pixels(), 0);
+
+        // Smooth sky needs fewer samples than the noisy spheres.
+        let counts: Vec<_> = (0..16 * 9).map(|index| whole.film.count(index)).collect();
+        assert!(counts.iter().all(|&n| (4..=32).contains(&n)));
+        assert!(counts.contains(&4) && counts.contains(&32));
+        let heatmap = whole.heatmap();
+        assert!(heatmap.chunks(4).any(|px| px == [255, 255, 255, 255]));
+        assert!(heatmap.chunks(4).any(|px| px == [95, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_filtered_tiles_match() {
+        let filter = Filter {
+            kind: FilterKind::Lanczos,
+            radius: 2.0,
+        };
+        let mut whole = Renderer::random(16, 9, 7);
+        whole.set_filter(filter);
+        whole.accumulate(2);
+
+        // Tiles take in the samples of their neighbours' edges.
+        let mut tiled = Renderer::random(16, 9, 7);
+        tiled.set_filter(filter);
+        tiled.accumulate_tile(0, 0, 8, 9, 2);
+        tiled.accumulate_tile(8, 0, 8, 9, 1);
+        tiled.accumulate_tile(8, 0, 8, 9, 1);
+        assert_eq!(whole.linear(), tiled.linear());
+
+        let mut unfiltered = Renderer::random(16, 9, 7);
+        unfiltered.accumulate(2);
+        assert_ne!(whole.linear(), unfiltered.linear());
+    }
+
+    #[test]
+    fn test_integrator() {
+        let mut renderer = Renderer::random(16, 9, 7);
+        renderer.accumulate(1);
+        let path = renderer.linear();
+
+        // Changes that don't alter the image keep the samples.
+        renderer.set_integrator(IntegratorKind::Path);
+        renderer.set_ao_distance(2.0);
+        assert_eq!(renderer.samples(), 1);
+
+        renderer.set_integrator(IntegratorKind::Normals);
+        assert_eq!(renderer.samples(), 0);
+        renderer.accumulate(1);
+        let normals = renderer.linear();
+        // The sky in the top left corner is lit, but has no normal.
+        assert_ne!(path[0], Color::default());
+        assert_eq!(normals[0], Color::default());
+        assert_ne!(normals, path);
+
+        // Material colours are the same for every renderer of the scene.
+        let material_id = || {
+            let mut renderer = Renderer::random(16, 9, 7);
+            renderer.set_integrator(IntegratorKind::MaterialId);
+            renderer.accumulate(1);
+            renderer.linear()
+        };
+        assert_eq!(material_id(), material_id());
+
+        renderer.set_integrator(IntegratorKind::AmbientOcclusion);
+        renderer.accumulate(1);
+        renderer.set_ao_distance(1.0);
+        assert_eq!(renderer.samples(), 0);
+    }
+}
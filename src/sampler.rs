@@ -0,0 +1,388 @@
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+// The source of random numbers for one camera sample, created once and
+// passed down to everything that needs a random decision along its path.
+// Each call takes the next dimension of the sample: the pixel position, then
+// the lens position, then the decisions made at each bounce.
+pub trait Sampler {
+    // A number in [0, 1).
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+// Which `Sampler` each camera sample draws from.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "independent" => Some(Self::Independent),
+            "stratified" => Some(Self::Stratified),
+            "halton" => Some(Self::Halton),
+            "sobol" => Some(Self::Sobol),
+            _ => None,
+        }
+    }
+}
+
+// Uniform random numbers, each independent of the others.
+pub struct IndependentSampler {
+    rng: SmallRng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn get_1d(&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+// Sample `index` of a pixel identified by `seed` lands in its own stratum of
+// each dimension, jittered within it, so every `samples_per_pixel` samples
+// cover the dimension evenly. Strata are shuffled separately for every
+// dimension so that they don't line up with each other.
+pub struct StratifiedSampler {
+    seed: u64,
+    index: u32,
+    samples_per_pixel: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, index: u32, samples_per_pixel: u32) -> Self {
+        Self {
+            seed,
+            index,
+            samples_per_pixel: samples_per_pixel.max(1),
+            dimension: 0,
+        }
+    }
+
+    // The stratum of `strata` this sample lands in for the next dimension,
+    // and a hash for jittering within it.
+    fn stratum(&mut self, strata: u32) -> (u32, u64) {
+        let round = self.index / strata;
+        let hash = mix(self.seed ^ mix((self.dimension as u64) << 32 | round as u64));
+        self.dimension += 1;
+        let stratum = permutation_element(self.index % strata, strata, hash as u32);
+        (stratum, mix(hash ^ self.index as u64))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn get_1d(&mut self) -> f64 {
+        let n = self.samples_per_pixel;
+        let (stratum, jitter) = self.stratum(n);
+        (stratum as f64 + to_unit(jitter)) / n as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        // The smallest grid with at least a cell per sample.
+        let nx = (self.samples_per_pixel as f64).sqrt().ceil() as u32;
+        let ny = self.samples_per_pixel.div_ceil(nx);
+        let (stratum, jitter) = self.stratum(nx * ny);
+        (
+            ((stratum % nx) as f64 + to_unit(jitter)) / nx as f64,
+            ((stratum / nx) as f64 + to_unit(mix(jitter))) / ny as f64,
+        )
+    }
+}
+
+// The Halton sequence, with a base for each dimension. Its digits are Owen
+// scrambled for each pixel, which also breaks up the correlation between
+// dimensions with large bases. Dimensions beyond the table of bases are
+// uniform random numbers.
+pub struct HaltonSampler {
+    seed: u64,
+    index: u32,
+    dimension: usize,
+}
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+impl HaltonSampler {
+    pub fn new(seed: u64, index: u32) -> Self {
+        Self {
+            seed,
+            index,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn get_1d(&mut self) -> f64 {
+        let hash = mix(self.seed ^ mix(self.dimension as u64));
+        let x = match PRIMES.get(self.dimension) {
+            Some(&base) => scrambled_radical_inverse(base, self.index, hash),
+            None => to_unit(mix(hash ^ self.index as u64)),
+        };
+        self.dimension += 1;
+        x.min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+// The first two dimensions of the Sobol sequence, used for every pair of
+// dimensions in turn. Each pair shuffles the order of the points and Owen
+// scrambles them, both by hashing, so that the pairs are independent of
+// each other while each keeps Sobol's stratification.
+pub struct SobolSampler {
+    seed: u64,
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64, index: u32) -> Self {
+        Self {
+            seed,
+            index,
+            dimension: 0,
+        }
+    }
+
+    // The shuffled index into the sequence for the next dimension, and the
+    // seed for scrambling its point.
+    fn next(&mut self) -> (u32, u64) {
+        let hash = mix(self.seed ^ mix(self.dimension as u64));
+        self.dimension += 1;
+        (owen_scramble(self.index, hash as u32), mix(hash))
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn get_1d(&mut self) -> f64 {
+        let (index, hash) = self.next();
+        let x = owen_scramble(index.reverse_bits(), hash as u32);
+        x as f64 / 2f64.powi(32)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, hash) = self.next();
+        let (x, y) = sobol_2d(index);
+        let (x, y) = (
+            owen_scramble(x, hash as u32),
+            owen_scramble(y, (hash >> 32) as u32),
+        );
+        (x as f64 / 2f64.powi(32), y as f64 / 2f64.powi(32))
+    }
+}
+
+// Whichever sampler a `SamplerKind` picks, held by value so that taking a
+// camera sample doesn't allocate.
+pub enum AnySampler {
+    Independent(IndependentSampler),
+    Stratified(StratifiedSampler),
+    Halton(HaltonSampler),
+    Sobol(SobolSampler),
+}
+
+impl Sampler for AnySampler {
+    fn get_1d(&mut self) -> f64 {
+        match self {
+            Self::Independent(s) => s.get_1d(),
+            Self::Stratified(s) => s.get_1d(),
+            Self::Halton(s) => s.get_1d(),
+            Self::Sobol(s) => s.get_1d(),
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        match self {
+            Self::Independent(s) => s.get_2d(),
+            Self::Stratified(s) => s.get_2d(),
+            Self::Halton(s) => s.get_2d(),
+            Self::Sobol(s) => s.get_2d(),
+        }
+    }
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+// SplitMix64's finaliser, which spreads nearby keys over the whole range.
+pub fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// The digits of `i` in `base`, mirrored about the radix point, with each
+// digit scrambled depending on the digits before it. The leading two digits
+// are randomly permuted, as in Owen scrambling; the rest matter less, so are
+// just shifted by a random amount, which is cheaper. Digits past the end of
+// `i` are scrambled too, down to about 32 bits of precision.
+fn scrambled_radical_inverse(base: u32, mut i: u32, seed: u64) -> f64 {
+    let (mut x, mut scale, mut prefix) = (0.0, 1.0 / base as f64, seed);
+    let mut leading = 2;
+    while scale > 1.0 / 2f64.powi(32) {
+        let digit = i % base;
+        let scrambled = if leading > 0 {
+            leading -= 1;
+            permutation_element(digit, base, prefix as u32)
+        } else {
+            ((digit as u64 + (prefix >> 32) % base as u64) % base as u64) as u32
+        };
+        x += scrambled as f64 * scale;
+        prefix = mix(prefix ^ (digit as u64 + 1));
+        i /= base;
+        scale /= base as f64;
+    }
+    x
+}
+
+// Point `i` of the first two dimensions of the Sobol sequence, as fixed point
+// fractions. The first is the van der Corput sequence.
+fn sobol_2d(i: u32) -> (u32, u32) {
+    let (mut y, mut v, mut bits) = (0, 1 << 31, i);
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= v;
+        }
+        v ^= v >> 1;
+        bits >>= 1;
+    }
+    (i.reverse_bits(), y)
+}
+
+// Burley's hash-based Owen scrambling: each bit is flipped depending on the
+// bits above it, which keeps the stratification of sequences like Sobol.
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits().wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+// Element `i` of a random permutation of 0..n chosen by `seed`, from
+// Kensler's "Correlated Multi-Jittered Sampling".
+fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return (i.wrapping_add(seed)) % n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        permutation_element, HaltonSampler, IndependentSampler, Sampler, SobolSampler,
+        StratifiedSampler,
+    };
+
+    #[test]
+    fn test_seeded() {
+        let draw = |seed| {
+            let mut s = IndependentSampler::new(seed);
+            (0..8).map(|_| s.get_1d()).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(3), draw(3));
+        assert_ne!(draw(3), draw(4));
+        assert!(draw(5).iter().all(|x| (0.0..1.0).contains(x)));
+    }
+
+    #[test]
+    fn test_permutation() {
+        let mut seen: Vec<_> = (0..10).map(|i| permutation_element(i, 10, 77)).collect();
+        seen.sort();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    }
+
+    // The mean squared error, over many pixels, of estimating the integral of
+    // a smooth function over the square with 16 samples each. The third pair
+    // of dimensions is used, as a bounce would.
+    fn error(new: impl Fn(u64, u32) -> Box<dyn Sampler>) -> f64 {
+        let f = |(x, y): (f64, f64)| x * y + (3.0 * x).sin();
+        let exact = 0.25 + (1.0 - 3f64.cos()) / 3.0;
+
+        let pixels = 400;
+        let total: f64 = (0..pixels)
+            .map(|pixel| {
+                let estimate = (0..16)
+                    .map(|index| {
+                        let mut sampler = new(pixel, index);
+                        sampler.get_2d();
+                        sampler.get_1d();
+                        let p = sampler.get_2d();
+                        assert!((0.0..1.0).contains(&p.0) && (0.0..1.0).contains(&p.1));
+                        f(p)
+                    })
+                    .sum::<f64>()
+                    / 16.0;
+                (estimate - exact).powi(2)
+            })
+            .sum();
+        total / pixels as f64
+    }
+
+    #[test]
+    fn test_lower_noise() {
+        let independent =
+            error(|pixel, index| Box::new(IndependentSampler::new(pixel << 32 | index as u64)));
+        let stratified = error(|pixel, index| Box::new(StratifiedSampler::new(pixel, index, 16)));
+        let halton = error(|pixel, index| Box::new(HaltonSampler::new(pixel, index)));
+        let sobol = error(|pixel, index| Box::new(SobolSampler::new(pixel, index)));
+
+        for better in [stratified, halton, sobol] {
+            assert!(better * 2.0 < independent, "{better} vs {independent}");
+        }
+    }
+}
@@ -0,0 +1,936 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::assets;
+use crate::camera::CameraSettings;
+use crate::environment::{Environment, EquirectEnvironment, GradientEnvironment, SolidEnvironment};
+use crate::film::Adaptive;
+use crate::filter::Filter;
+use crate::hittable::{Hittable, HittableList};
+use crate::image::Image;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, Numbered};
+use crate::mesh::TriangleMesh;
+use crate::obj::load_numbered_obj;
+use crate::sampler::SamplerKind;
+use crate::sphere::Sphere;
+use crate::texture::{
+    CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture, WrapMode,
+};
+use crate::tonemap::ToneMapping;
+use crate::triangle::Triangle;
+use crate::vec3::{Color, Vec3};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneError {
+    // The JSON is malformed, or a field has the wrong type or is missing.
+    Parse { field: String, message: String },
+    // A field is well-formed but its value is out of range.
+    Invalid { field: String, message: String },
+    UnknownMaterial { field: String, name: String },
+}
+
+impl SceneError {
+    // The path of the offending field, such as `objects[2].radius`.
+    pub fn field(&self) -> &str {
+        match self {
+            Self::Parse { field, .. } | Self::Invalid { field, .. } => field,
+            Self::UnknownMaterial { field, .. } => field,
+        }
+    }
+
+    fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse { field, message } if field.is_empty() => write!(f, "{message}"),
+            Self::Parse { field, message } | Self::Invalid { field, message } => {
+                write!(f, "{field}: {message}")
+            }
+            Self::UnknownMaterial { field, name } => {
+                write!(f, "{field}: unknown material \"{name}\"")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub samples_per_pixel: u32,
+    pub max_depth: u16,
+    // How many times every path bounces before Russian roulette may end it.
+    pub min_bounces: u16,
+    pub sampler: SamplerKind,
+    // Unless `None`, passes only sample pixels that haven't converged.
+    pub adaptive: Option<Adaptive>,
+    pub filter: Filter,
+    pub tone_mapping: ToneMapping,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            samples_per_pixel: 10,
+            max_depth: 50,
+            min_bounces: 3,
+            sampler: SamplerKind::Independent,
+            adaptive: None,
+            filter: Filter::default(),
+            tone_mapping: ToneMapping::default(),
+        }
+    }
+}
+
+pub struct Scene {
+    pub world: HittableList,
+    pub camera: CameraSettings,
+    pub environment: Box<dyn Environment>,
+    pub settings: RenderSettings,
+}
+
+impl Scene {
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        let value: Value = serde_json::from_str(json).map_err(|e| SceneError::Parse {
+            field: String::new(),
+            message: e.to_string(),
+        })?;
+
+        parse::<SceneDesc>("", value)?.build()
+    }
+}
+
+// Deserializes `value`, reporting errors against the path of the field that
+// failed beneath `field`.
+fn parse<T: DeserializeOwned>(field: &str, value: Value) -> Result<T, SceneError> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = e.path().to_string();
+        let field = match (field, path.as_str()) {
+            (field, ".") => field.to_string(),
+            ("", path) => path.to_string(),
+            (field, path) if path.starts_with('[') => format!("{field}{path}"),
+            (field, path) => format!("{field}.{path}"),
+        };
+        SceneError::Parse {
+            field,
+            message: e.into_inner().to_string(),
+        }
+    })
+}
+
+// Splits the tag off an object like `{ "type": "sphere", ... }`. Tagged
+// objects are dispatched by hand rather than with serde's internally tagged
+// enums, which lose track of the field that failed to parse.
+fn tagged(field: &str, value: Value) -> Result<(String, Value), SceneError> {
+    let mut map = match value {
+        Value::Object(map) => map,
+        _ => {
+            return Err(SceneError::Parse {
+                field: field.to_string(),
+                message: "expected an object with a \"type\"".to_string(),
+            })
+        }
+    };
+
+    match map.remove("type") {
+        Some(Value::String(tag)) => Ok((tag, Value::Object(map))),
+        _ => Err(SceneError::Parse {
+            field: format!("{field}.type"),
+            message: "expected a string".to_string(),
+        }),
+    }
+}
+
+fn unknown_type(field: &str, kind: &str, tag: &str) -> SceneError {
+    SceneError::invalid(
+        format!("{field}.type"),
+        format!("unknown {kind} type \"{tag}\""),
+    )
+}
+
+type Vec3Desc = [f64; 3];
+
+fn vec3(v: Vec3Desc) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color_field(field: &str, c: Vec3Desc) -> Result<Color, SceneError> {
+    if c.iter().any(|&x| x < 0.0) {
+        return Err(SceneError::invalid(field, "must not be negative"));
+    }
+    Ok(vec3(c))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: CameraDesc,
+    background: Option<Value>,
+    #[serde(default)]
+    materials: HashMap<String, Value>,
+    #[serde(default)]
+    objects: Vec<Value>,
+    #[serde(default)]
+    render: RenderSettings,
+}
+
+impl SceneDesc {
+    fn build(self) -> Result<Scene, SceneError> {
+        let camera = self.camera.build()?;
+
+        let environment = match self.background {
+            Some(value) => build_environment("background", value)?,
+            None => Box::new(GradientEnvironment::default()),
+        };
+
+        // Numbered in name order, which unlike the map's doesn't change from
+        // run to run.
+        let mut descs: Vec<_> = self.materials.into_iter().collect();
+        descs.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut materials = HashMap::new();
+        for (id, (name, value)) in descs.into_iter().enumerate() {
+            let mat = build_material(&format!("materials.{name}"), value)?;
+            let mat: Arc<dyn Material> = Arc::new(Numbered::new(id, mat));
+            materials.insert(name, mat);
+        }
+
+        // OBJ models number their materials on from the scene's.
+        let mut next_id = materials.len();
+        let mut world = HittableList::new();
+        for (i, value) in self.objects.into_iter().enumerate() {
+            let field = format!("objects[{i}]");
+            build_object(&field, value, &materials, &mut next_id, &mut world)?;
+        }
+
+        let settings = self.render;
+        if settings.samples_per_pixel == 0 {
+            return Err(SceneError::invalid(
+                "render.samples_per_pixel",
+                "must be at least 1",
+            ));
+        }
+        if settings.max_depth == 0 {
+            return Err(SceneError::invalid(
+                "render.max_depth",
+                "must be at least 1",
+            ));
+        }
+        if let Some(Err((field, message))) = settings.adaptive.map(|a| a.check()) {
+            return Err(SceneError::invalid(
+                format!("render.adaptive.{field}"),
+                message,
+            ));
+        }
+        if settings.filter.radius <= 0.0 {
+            return Err(SceneError::invalid(
+                "render.filter.radius",
+                "must be positive",
+            ));
+        }
+        if settings.tone_mapping.white <= 0.0 {
+            return Err(SceneError::invalid(
+                "render.tone_mapping.white",
+                "must be positive",
+            ));
+        }
+
+        Ok(Scene {
+            world,
+            camera,
+            environment,
+            settings,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    lookfrom: Vec3Desc,
+    lookat: Vec3Desc,
+    #[serde(default = "CameraDesc::default_vup")]
+    vup: Vec3Desc,
+    #[serde(default = "CameraDesc::default_vfov")]
+    vfov: f64,
+    #[serde(default)]
+    aperture: f64,
+    // Defaults to the distance between `lookfrom` and `lookat`.
+    focus_dist: Option<f64>,
+}
+
+impl CameraDesc {
+    fn default_vup() -> Vec3Desc {
+        [0.0, 1.0, 0.0]
+    }
+
+    fn default_vfov() -> f64 {
+        40.0
+    }
+
+    fn build(self) -> Result<CameraSettings, SceneError> {
+        let (lookfrom, lookat) = (vec3(self.lookfrom), vec3(self.lookat));
+        let vup = vec3(self.vup);
+
+        if (lookfrom - lookat).near_zero() {
+            return Err(SceneError::invalid(
+                "camera.lookat",
+                "must differ from camera.lookfrom",
+            ));
+        }
+        if vup.near_zero() || (lookfrom - lookat).unit().cross(vup.unit()).near_zero() {
+            return Err(SceneError::invalid(
+                "camera.vup",
+                "must be non-zero and not parallel to the view direction",
+            ));
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(SceneError::invalid(
+                "camera.vfov",
+                "must be between 0 and 180 degrees",
+            ));
+        }
+        if self.aperture < 0.0 {
+            return Err(SceneError::invalid(
+                "camera.aperture",
+                "must not be negative",
+            ));
+        }
+
+        let focus_dist = self.focus_dist.unwrap_or((lookfrom - lookat).length());
+        if focus_dist <= 0.0 {
+            return Err(SceneError::invalid("camera.focus_dist", "must be positive"));
+        }
+
+        Ok(CameraSettings {
+            lookfrom,
+            lookat,
+            vup,
+            vfov: self.vfov,
+            aperture: self.aperture,
+            focus_dist,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SolidDesc {
+    color: Vec3Desc,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GradientDesc {
+    horizon: Option<Vec3Desc>,
+    zenith: Option<Vec3Desc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EquirectDesc {
+    image: String,
+}
+
+fn build_environment(field: &str, value: Value) -> Result<Box<dyn Environment>, SceneError> {
+    let (tag, value) = tagged(field, value)?;
+    Ok(match tag.as_str() {
+        "solid" => {
+            let desc: SolidDesc = parse(field, value)?;
+            let color = color_field(&format!("{field}.color"), desc.color)?;
+            Box::new(SolidEnvironment::new(color))
+        }
+        // "sky" is the name scenes used before gradients took colours.
+        "gradient" | "sky" => {
+            let desc: GradientDesc = parse(field, value)?;
+            let default = GradientEnvironment::default();
+            let color = |name, c: Option<Vec3Desc>, fallback| match c {
+                Some(c) => color_field(&format!("{field}.{name}"), c),
+                None => Ok(fallback),
+            };
+            Box::new(GradientEnvironment::new(
+                color("horizon", desc.horizon, default.horizon())?,
+                color("zenith", desc.zenith, default.zenith())?,
+            ))
+        }
+        "equirect" => {
+            let desc: EquirectDesc = parse(field, value)?;
+            let image = registered_image(&format!("{field}.image"), &desc.image)?;
+            Box::new(EquirectEnvironment::new(image))
+        }
+        tag => return Err(unknown_type(field, "background", tag)),
+    })
+}
+
+fn registered_image(field: &str, name: &str) -> Result<Arc<Image>, SceneError> {
+    assets::image(name).ok_or_else(|| {
+        SceneError::invalid(field, format!("no image named \"{name}\" has been loaded"))
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckerDesc {
+    scale: f64,
+    even: Value,
+    odd: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageTextureDesc {
+    image: String,
+    #[serde(default = "default_wrap")]
+    wrap: WrapModeDesc,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WrapModeDesc {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+fn default_wrap() -> WrapModeDesc {
+    WrapModeDesc::Repeat
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NoiseStyleDesc {
+    Smooth,
+    Turbulence,
+    Marble,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseDesc {
+    #[serde(default = "default_noise_scale")]
+    scale: f64,
+    #[serde(default = "default_noise_style")]
+    style: NoiseStyleDesc,
+}
+
+fn default_noise_scale() -> f64 {
+    1.0
+}
+
+fn default_noise_style() -> NoiseStyleDesc {
+    NoiseStyleDesc::Smooth
+}
+
+// Textures can also be written as a plain `[r, g, b]` colour.
+fn build_texture(field: &str, value: Value) -> Result<Arc<dyn Texture>, SceneError> {
+    if value.is_array() {
+        let color = color_field(field, parse(field, value)?)?;
+        return Ok(Arc::new(SolidColor::new(color)));
+    }
+
+    let (tag, value) = tagged(field, value)?;
+    Ok(match tag.as_str() {
+        "solid" => {
+            let desc: SolidDesc = parse(field, value)?;
+            let color = color_field(&format!("{field}.color"), desc.color)?;
+            Arc::new(SolidColor::new(color))
+        }
+        "checker" => {
+            let desc: CheckerDesc = parse(field, value)?;
+            if desc.scale <= 0.0 {
+                return Err(SceneError::invalid(
+                    format!("{field}.scale"),
+                    "must be positive",
+                ));
+            }
+            Arc::new(CheckerTexture::new(
+                desc.scale,
+                build_texture(&format!("{field}.even"), desc.even)?,
+                build_texture(&format!("{field}.odd"), desc.odd)?,
+            ))
+        }
+        "image" => {
+            let desc: ImageTextureDesc = parse(field, value)?;
+            let image = registered_image(&format!("{field}.image"), &desc.image)?;
+            let wrap = match desc.wrap {
+                WrapModeDesc::Repeat => WrapMode::Repeat,
+                WrapModeDesc::Clamp => WrapMode::Clamp,
+                WrapModeDesc::Mirror => WrapMode::Mirror,
+            };
+            Arc::new(ImageTexture::new(image, wrap))
+        }
+        "noise" => {
+            let desc: NoiseDesc = parse(field, value)?;
+            let style = match desc.style {
+                NoiseStyleDesc::Smooth => NoiseStyle::Smooth,
+                NoiseStyleDesc::Turbulence => NoiseStyle::Turbulence,
+                NoiseStyleDesc::Marble => NoiseStyle::Marble,
+            };
+            Arc::new(NoiseTexture::new(style, desc.scale))
+        }
+        tag => return Err(unknown_type(field, "texture", tag)),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LambertianDesc {
+    albedo: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MetalDesc {
+    albedo: Vec3Desc,
+    fuzz: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DielectricDesc {
+    ir: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiffuseLightDesc {
+    emit: Vec3Desc,
+}
+
+fn build_material(field: &str, value: Value) -> Result<Arc<dyn Material>, SceneError> {
+    let (tag, value) = tagged(field, value)?;
+    Ok(match tag.as_str() {
+        "lambertian" => {
+            let desc: LambertianDesc = parse(field, value)?;
+            let albedo = build_texture(&format!("{field}.albedo"), desc.albedo)?;
+            Arc::new(Lambertian::textured(albedo))
+        }
+        "metal" => {
+            let desc: MetalDesc = parse(field, value)?;
+            let albedo = color_field(&format!("{field}.albedo"), desc.albedo)?;
+            if !(0.0..=1.0).contains(&desc.fuzz) {
+                return Err(SceneError::invalid(
+                    format!("{field}.fuzz"),
+                    "must be between 0 and 1",
+                ));
+            }
+            Arc::new(Metal::new(albedo, desc.fuzz))
+        }
+        "dielectric" => {
+            let desc: DielectricDesc = parse(field, value)?;
+            if desc.ir <= 0.0 {
+                return Err(SceneError::invalid(
+                    format!("{field}.ir"),
+                    "must be positive",
+                ));
+            }
+            Arc::new(Dielectric::new(desc.ir))
+        }
+        "diffuse_light" => {
+            let desc: DiffuseLightDesc = parse(field, value)?;
+            let emit = color_field(&format!("{field}.emit"), desc.emit)?;
+            Arc::new(DiffuseLight::new(emit))
+        }
+        tag => return Err(unknown_type(field, "material", tag)),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDesc {
+    center: Vec3Desc,
+    radius: f64,
+    material: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDesc {
+    vertices: [Vec3Desc; 3],
+    material: String,
+}
+
+// A parallelogram with corner `q` and edges `u` and `v`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuadDesc {
+    q: Vec3Desc,
+    u: Vec3Desc,
+    v: Vec3Desc,
+    material: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDesc {
+    positions: Vec<Vec3Desc>,
+    #[serde(default)]
+    normals: Vec<Vec3Desc>,
+    #[serde(default)]
+    uvs: Vec<[f64; 2]>,
+    indices: Vec<[u32; 3]>,
+    material: String,
+}
+
+// An inline Wavefront OBJ model, with the MTL library it uses.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjDesc {
+    source: String,
+    mtl: Option<String>,
+}
+
+// Objects made of emissive materials are sampled as lights too.
+fn add<H: Hittable + 'static>(world: &mut HittableList, object: H, mat: &Arc<dyn Material>) {
+    if mat.is_emissive() {
+        world.add_light(Arc::new(object));
+    } else {
+        world.add(Box::new(object));
+    }
+}
+
+fn build_object(
+    field: &str,
+    value: Value,
+    materials: &HashMap<String, Arc<dyn Material>>,
+    next_id: &mut usize,
+    world: &mut HittableList,
+) -> Result<(), SceneError> {
+    let material = |name: String| {
+        materials
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| SceneError::UnknownMaterial {
+                field: format!("{field}.material"),
+                name,
+            })
+    };
+
+    let (tag, value) = tagged(field, value)?;
+    match tag.as_str() {
+        "sphere" => {
+            let desc: SphereDesc = parse(field, value)?;
+            if desc.radius <= 0.0 {
+                return Err(SceneError::invalid(
+                    format!("{field}.radius"),
+                    "must be positive",
+                ));
+            }
+            let mat = material(desc.material)?;
+            let sphere = Sphere::new(vec3(desc.center), desc.radius, mat.clone());
+            add(world, sphere, &mat);
+        }
+        "triangle" => {
+            let desc: TriangleDesc = parse(field, value)?;
+            let [a, b, c] = desc.vertices.map(vec3);
+            if (b - a).cross(c - a).near_zero() {
+                return Err(SceneError::invalid(
+                    format!("{field}.vertices"),
+                    "must not be collinear",
+                ));
+            }
+            let mat = material(desc.material)?;
+            add(world, Triangle::new(a, b, c, mat.clone()), &mat);
+        }
+        "quad" => {
+            let desc: QuadDesc = parse(field, value)?;
+            let (q, u, v) = (vec3(desc.q), vec3(desc.u), vec3(desc.v));
+            if u.cross(v).near_zero() {
+                return Err(SceneError::invalid(
+                    format!("{field}.v"),
+                    "must not be parallel to u",
+                ));
+            }
+            let mat = material(desc.material)?;
+            add(world, TriangleMesh::quad(q, u, v, mat.clone()), &mat);
+        }
+        "mesh" => {
+            let desc: MeshDesc = parse(field, value)?;
+            let mat = material(desc.material)?;
+            let mesh = TriangleMesh::new(
+                desc.positions.into_iter().map(vec3).collect(),
+                desc.normals.into_iter().map(vec3).collect(),
+                desc.uvs.into_iter().map(|[u, v]| (u, v)).collect(),
+                desc.indices,
+                mat.clone(),
+            )
+            .map_err(|e| SceneError::invalid(field, e.to_string()))?;
+            add(world, mesh, &mat);
+        }
+        "obj" => {
+            let desc: ObjDesc = parse(field, value)?;
+            let model = load_numbered_obj(&desc.source, desc.mtl.as_deref(), next_id)
+                .map_err(|e| SceneError::invalid(format!("{field}.source"), e.to_string()))?;
+            for obj in model.into_objects() {
+                world.add(obj);
+            }
+        }
+        tag => return Err(unknown_type(field, "object", tag)),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scene, SceneError};
+    use crate::assets;
+    use crate::environment::{Environment, GradientEnvironment};
+    use crate::filter::FilterKind;
+    use crate::hittable::Hittable;
+    use crate::image::Image;
+    use crate::ray::Ray;
+    use crate::sampler::SamplerKind;
+    use crate::tonemap::ToneMap;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    const SCENE: &str = r#"{
+        "camera": { "lookfrom": [0, 0, 5], "lookat": [0, 0, 0], "vfov": 30 },
+        "background": { "type": "solid", "color": [0.1, 0.1, 0.1] },
+        "materials": {
+            "red": { "type": "lambertian", "albedo": [0.8, 0.1, 0.1] },
+            "glass": { "type": "dielectric", "ir": 1.5 }
+        },
+        "objects": [
+            { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "red" },
+            {
+                "type": "mesh",
+                "positions": [[-2, -2, -1], [2, -2, -1], [0, 2, -1]],
+                "indices": [[0, 1, 2]],
+                "material": "glass"
+            }
+        ],
+        "render": {
+            "samples_per_pixel": 4,
+            "min_bounces": 5,
+            "sampler": "sobol",
+            "adaptive": { "threshold": 0.05 },
+            "filter": { "type": "mitchell", "radius": 2 },
+            "tone_mapping": { "operator": "extended_reinhard", "white": 2 }
+        }
+    }"#;
+
+    fn error(json: &str) -> SceneError {
+        Scene::from_json(json).err().unwrap()
+    }
+
+    #[test]
+    fn test_from_json() {
+        let scene = Scene::from_json(SCENE).unwrap();
+        assert_eq!(scene.camera.lookfrom, Point3::new(0.0, 0.0, 5.0));
+        assert_eq!(scene.camera.focus_dist, 5.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(scene.environment.color(up), Color::new(0.1, 0.1, 0.1));
+        assert_eq!(scene.settings.samples_per_pixel, 4);
+        assert_eq!(
+            (scene.settings.max_depth, scene.settings.min_bounces),
+            (50, 5)
+        );
+        assert_eq!(scene.settings.sampler, SamplerKind::Sobol);
+        let adaptive = scene.settings.adaptive.unwrap();
+        assert_eq!((adaptive.threshold, adaptive.min_samples), (0.05, 16));
+        let filter = scene.settings.filter;
+        assert_eq!((filter.kind, filter.radius), (FilterKind::Mitchell, 2.0));
+        let tone_mapping = scene.settings.tone_mapping;
+        assert_eq!(tone_mapping.operator, ToneMap::ExtendedReinhard);
+        assert_eq!((tone_mapping.exposure, tone_mapping.white), (0.0, 2.0));
+
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let i = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((i.t - 4.0).abs() < 1e-12);
+
+        let r = Ray::new(Point3::new(1.5, -1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let i = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((i.t - 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let err = error(&SCENE.replace(r#""radius": 1"#, r#""radius": -1"#));
+        assert_eq!(err.field(), "objects[0].radius");
+        assert_eq!(err.to_string(), "objects[0].radius: must be positive");
+
+        let err = error(&SCENE.replace(r#""material": "red""#, r#""material": "blue""#));
+        assert_eq!(
+            err,
+            SceneError::UnknownMaterial {
+                field: "objects[0].material".to_string(),
+                name: "blue".to_string(),
+            }
+        );
+
+        let err = error(&SCENE.replace(r#""ir": 1.5"#, r#""ir": 0"#));
+        assert_eq!(err.field(), "materials.glass.ir");
+
+        let err = error(&SCENE.replace("[[0, 1, 2]]", "[[0, 1, 3]]"));
+        assert_eq!(err.field(), "objects[1]");
+
+        let sphere = r#"{ "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "red" }"#;
+        let triangle = |vertices: &str| {
+            let desc =
+                format!(r#"{{ "type": "triangle", "vertices": {vertices}, "material": "red" }}"#);
+            SCENE.replace(sphere, &desc)
+        };
+        assert!(Scene::from_json(&triangle("[[0, 0, 0], [1, 0, 0], [0, 1, 0]]")).is_ok());
+        let err = error(&triangle("[[0, 0, 0], [1, 1, 1], [2, 2, 2]]"));
+        assert_eq!(
+            err.to_string(),
+            "objects[0].vertices: must not be collinear"
+        );
+        let err = error(&triangle("[[0, 0, 0], [1, 0, 0], [1, 0, 0]]"));
+        assert_eq!(err.field(), "objects[0].vertices");
+
+        let err = error(&SCENE.replace(r#""threshold": 0.05"#, r#""max_samples": 8"#));
+        assert_eq!(
+            err.to_string(),
+            "render.adaptive.max_samples: must be at least min_samples"
+        );
+
+        let err = error(&SCENE.replace(r#""radius": 2"#, r#""radius": -2"#));
+        assert_eq!(err.field(), "render.filter.radius");
+
+        let err = error(&SCENE.replace(r#""white": 2"#, r#""white": 0"#));
+        assert_eq!(err.field(), "render.tone_mapping.white");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = error(&SCENE.replace(r#""radius": 1"#, r#""radius": "big""#));
+        assert!(matches!(err, SceneError::Parse { .. }));
+        assert_eq!(err.field(), "objects[0].radius");
+
+        let err = error(&SCENE.replace(r#""vfov": 30"#, r#""fov": 30"#));
+        assert_eq!(err.field(), "camera.fov");
+
+        let err = error(&SCENE.replace(r#""type": "lambertian""#, r#""type": "chalk""#));
+        assert_eq!(
+            err.to_string(),
+            r#"materials.red.type: unknown material type "chalk""#
+        );
+
+        let err = error(&SCENE.replace("extended_reinhard", "filmic"));
+        assert_eq!(err.field(), "render.tone_mapping.operator");
+
+        assert!(matches!(error("{"), SceneError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_textures() {
+        let checker = r#"{
+            "type": "checker",
+            "scale": 0.5,
+            "even": [1, 1, 1],
+            "odd": { "type": "noise", "style": "marble", "scale": 4 }
+        }"#;
+        let json = SCENE.replace("[0.8, 0.1, 0.1]", checker);
+        assert!(Scene::from_json(&json).is_ok());
+
+        let err = error(&json.replace(r#""scale": 0.5"#, r#""scale": 0"#));
+        assert_eq!(err.field(), "materials.red.albedo.scale");
+
+        let err = error(&json.replace(r#""style": "marble""#, r#""style": "wood""#));
+        assert_eq!(err.field(), "materials.red.albedo.odd.style");
+
+        let err = error(&json.replace("[1, 1, 1]", r#"{ "type": "image", "image": "nowhere" }"#));
+        assert_eq!(
+            err.to_string(),
+            r#"materials.red.albedo.even.image: no image named "nowhere" has been loaded"#
+        );
+    }
+
+    #[test]
+    fn test_sky_background() {
+        let json = SCENE.replace(
+            r#"{ "type": "solid", "color": [0.1, 0.1, 0.1] }"#,
+            r#"{ "type": "sky" }"#,
+        );
+        let scene = Scene::from_json(&json).unwrap();
+        let sky = GradientEnvironment::default();
+        for dir in [Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)] {
+            assert_eq!(scene.environment.color(dir), sky.color(dir));
+        }
+    }
+
+    #[test]
+    fn test_equirect_background() {
+        let background = r#"{ "type": "equirect", "image": "scene-test-sky" }"#;
+        let json = SCENE.replace(
+            r#"{ "type": "solid", "color": [0.1, 0.1, 0.1] }"#,
+            background,
+        );
+
+        let err = error(&json);
+        assert_eq!(err.field(), "background.image");
+
+        let pixels = vec![Color::new(0.25, 0.5, 1.0); 8];
+        assets::register_image("scene-test-sky", Image::new(4, 2, pixels).unwrap());
+        let scene = Scene::from_json(&json).unwrap();
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(scene.environment.color(up), Color::new(0.25, 0.5, 1.0));
+    }
+
+    #[test]
+    fn test_cornell_box() {
+        let json = r#"{
+            "camera": { "lookfrom": [278, 278, -800], "lookat": [278, 278, 0] },
+            "background": { "type": "solid", "color": [0, 0, 0] },
+            "materials": {
+                "red": { "type": "lambertian", "albedo": [0.65, 0.05, 0.05] },
+                "white": { "type": "lambertian", "albedo": [0.73, 0.73, 0.73] },
+                "green": { "type": "lambertian", "albedo": [0.12, 0.45, 0.15] },
+                "light": { "type": "diffuse_light", "emit": [15, 15, 15] }
+            },
+            "objects": [
+                { "type": "quad", "q": [555, 0, 0], "u": [0, 555, 0], "v": [0, 0, 555], "material": "green" },
+                { "type": "quad", "q": [0, 0, 0], "u": [0, 555, 0], "v": [0, 0, 555], "material": "red" },
+                { "type": "quad", "q": [343, 554, 332], "u": [-130, 0, 0], "v": [0, 0, -105], "material": "light" },
+                { "type": "quad", "q": [0, 0, 0], "u": [555, 0, 0], "v": [0, 0, 555], "material": "white" },
+                { "type": "quad", "q": [555, 555, 555], "u": [-555, 0, 0], "v": [0, 0, -555], "material": "white" },
+                { "type": "quad", "q": [0, 0, 555], "u": [555, 0, 0], "v": [0, 555, 0], "material": "white" }
+            ]
+        }"#;
+        let scene = Scene::from_json(json).unwrap();
+
+        let r = Ray::new(Point3::new(278.0, 278.0, 278.0), Vec3::new(0.0, 1.0, 0.0));
+        let i = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((i.t - 276.0).abs() < 1e-9);
+        assert_eq!(i.mat.emitted(i.u, i.v, i.p), Color::new(15.0, 15.0, 15.0));
+
+        let err = error(&json.replace("[0, 0, -105]", "[-260, 0, 0]"));
+        assert_eq!(err.field(), "objects[2].v");
+    }
+
+    #[test]
+    fn test_obj_material_ids() {
+        let json = r#"{
+            "camera": { "lookfrom": [0, 0, 5], "lookat": [0, 0, 0] },
+            "materials": { "red": { "type": "lambertian", "albedo": [1, 0, 0] } },
+            "objects": [
+                { "type": "sphere", "center": [-2, 0, 0], "radius": 0.5, "material": "red" },
+                {
+                    "type": "obj",
+                    "source": "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nv 3 0 0\nv 2 1 0\nf 1 2 3\nusemtl blue\nf 4 5 6",
+                    "mtl": "newmtl blue\nKd 0 0 1"
+                }
+            ]
+        }"#;
+        let scene = Scene::from_json(json).unwrap();
+        let id = |x: f64| {
+            let r = Ray::new(Point3::new(x, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            scene.world.hit(&r, 0.001, f64::INFINITY).unwrap().mat.id()
+        };
+        assert_eq!((id(-2.0), id(0.25), id(2.25)), (0, 1, 2));
+    }
+}
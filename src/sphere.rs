@@ -1,7 +1,11 @@
+use std::f64::consts::PI;
+
+use crate::aabb::Aabb;
 use crate::hittable::{Hittable, Intersection};
 use crate::material::Material;
+use crate::onb::Onb;
 use crate::ray::Ray;
-use crate::vec3::Point3;
+use crate::vec3::{Point3, Vec3};
 
 pub struct Sphere<M: Material> {
     center: Point3,
@@ -17,10 +21,21 @@ impl<M: Material> Sphere<M> {
             mat,
         }
     }
+
+    // One minus the cosine of the half-angle of the cone the sphere fills as
+    // seen from `origin`, or `None` from inside it. Written to keep its
+    // precision for small, distant spheres.
+    fn cone(&self, origin: Point3) -> Option<f64> {
+        let sin2 = self.radius * self.radius / (self.center - origin).length_squared();
+        if sin2 >= 1.0 {
+            return None;
+        }
+        Some(sin2 / (1.0 + (1.0 - sin2).sqrt()))
+    }
 }
 
-impl<M: Material> Hittable for Sphere<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection> {
+impl<M: Material + 'static> Hittable for Sphere<M> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
         let oc = r.origin() - self.center;
         let (a, half_b, c) = (
             r.direction().length_squared(),
@@ -50,6 +65,83 @@ impl<M: Material> Hittable for Sphere<M> {
             (-outward_normal, false)
         };
 
-        Some(Intersection::new(p, normal, &self.mat, t, front_face))
+        Some(Intersection::new(
+            p,
+            normal,
+            &self.mat,
+            t,
+            sphere_uv(outward_normal),
+            front_face,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius.abs();
+        let r = Vec3::new(r, r, r);
+        Aabb::new(self.center - r, self.center + r)
+    }
+
+    // Picks directions uniformly within the cone the sphere fills, so every
+    // one of them reaches it.
+    fn sample(&self, origin: Point3, (u1, u2): (f64, f64)) -> Option<Vec3> {
+        let one_minus_cos = self.cone(origin)?;
+        let z = 1.0 - u2 * one_minus_cos;
+        let (r, phi) = ((1.0 - z * z).max(0.0).sqrt(), 2.0 * PI * u1);
+        let onb = Onb::new(self.center - origin);
+        Some(onb.local(Vec3::new(r * phi.cos(), r * phi.sin(), z)).unit())
+    }
+
+    fn pdf(&self, origin: Point3, dir: Vec3) -> f64 {
+        let r = Ray::new(origin, dir);
+        match self.cone(origin) {
+            Some(one_minus_cos) if self.hit(&r, 0.001, f64::INFINITY).is_some() => {
+                1.0 / (2.0 * PI * one_minus_cos)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+// Texture coordinates of a point on the unit sphere: u runs around the y axis
+// starting from -x, and v from the bottom pole to the top.
+fn sphere_uv(p: Point3) -> (f64, f64) {
+    let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sphere;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    #[test]
+    fn test_hit_uv() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            2.0,
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        );
+        let uv = |org: Point3, dir: Vec3| {
+            let i = sphere
+                .hit(&Ray::new(org, dir), 0.001, f64::INFINITY)
+                .unwrap();
+            (i.u, i.v)
+        };
+
+        // Looking down -z hits the +z side of the sphere, a quarter turn
+        // from -x.
+        let (u, v) = uv(Point3::default(), Vec3::new(0.0, 0.0, -1.0));
+        assert!((u - 0.25).abs() < 1e-12 && (v - 0.5).abs() < 1e-12);
+
+        let (u, v) = uv(Point3::new(-5.0, 0.0, -3.0), Vec3::new(1.0, 0.0, 0.0));
+        // The seam, where u wraps from 1 back to 0.
+        assert!(u.min(1.0 - u) < 1e-12 && (v - 0.5).abs() < 1e-12);
+
+        let (_, v) = uv(Point3::new(0.0, 5.0, -3.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!((v - 1.0).abs() < 1e-12);
     }
 }
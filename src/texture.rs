@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, SeedableRng};
+
+use crate::image::Image;
+use crate::perlin::Perlin;
+use crate::vec3::{Color, Point3};
+
+// A colour that varies over a surface, looked up by texture coordinates and
+// the hit point. Textures are shared between materials behind an `Arc`, so
+// they must be safe to share.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        (**self).value(u, v, p)
+    }
+}
+
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.color
+    }
+}
+
+// Alternates between two textures in cubes of side `scale`, so it works on
+// any surface whether it has texture coordinates or not.
+pub struct CheckerTexture {
+    scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { scale, even, odd }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let cell = (p.x() / self.scale).floor()
+            + (p.y() / self.scale).floor()
+            + (p.z() / self.scale).floor();
+        if cell.rem_euclid(2.0) == 0.0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// How texture coordinates outside [0, 1] map back onto an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    // Edge pixels are stretched outwards.
+    Clamp,
+    // Every other tile is flipped, so the edges always meet.
+    Mirror,
+}
+
+impl WrapMode {
+    fn wrap(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            Self::Repeat => i.rem_euclid(n),
+            Self::Clamp => i.clamp(0, n - 1),
+            Self::Mirror => match i.rem_euclid(2 * n) {
+                i if i < n => i,
+                i => 2 * n - 1 - i,
+            },
+        };
+        i as usize
+    }
+}
+
+// An image mapped onto the texture coordinates, with v = 0 at the bottom row,
+// and filtered bilinearly between pixel centres.
+pub struct ImageTexture {
+    image: Arc<Image>,
+    wrap: WrapMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Image>, wrap: WrapMode) -> Self {
+        Self { image, wrap }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        let s = u * width as f64 - 0.5;
+        let t = (1.0 - v) * height as f64 - 0.5;
+        let (x, y) = (s.floor(), t.floor());
+        let (fx, fy) = (s - x, t - y);
+
+        let pixel = |dx, dy| {
+            self.image.pixel(
+                self.wrap.wrap(x as i64 + dx, width),
+                self.wrap.wrap(y as i64 + dy, height),
+            )
+        };
+        let top = (1.0 - fx) * pixel(0, 0) + fx * pixel(1, 0);
+        let bottom = (1.0 - fx) * pixel(0, 1) + fx * pixel(1, 1);
+        (1.0 - fy) * top + fy * bottom
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseStyle {
+    // Plain Perlin noise.
+    Smooth,
+    // Several octaves of noise, which looks like clouds or smoke.
+    Turbulence,
+    // Sine stripes along z, bent by turbulence.
+    Marble,
+}
+
+// Greyscale Perlin noise. `scale` is the frequency of the pattern in world
+// space.
+pub struct NoiseTexture {
+    perlin: Perlin,
+    scale: f64,
+    style: NoiseStyle,
+}
+
+impl NoiseTexture {
+    pub fn new(style: NoiseStyle, scale: f64) -> Self {
+        // A fixed seed keeps the pattern the same from render to render.
+        let mut rng = SmallRng::seed_from_u64(0);
+        Self {
+            perlin: Perlin::new(&mut rng),
+            scale,
+            style,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let p = self.scale * p;
+        let grey = match self.style {
+            NoiseStyle::Smooth => 0.5 * (1.0 + self.perlin.noise(p)),
+            NoiseStyle::Turbulence => self.perlin.turbulence(p, 7),
+            NoiseStyle::Marble => 0.5 * (1.0 + (p.z() + 10.0 * self.perlin.turbulence(p, 7)).sin()),
+        };
+        grey.clamp(0.0, 1.0) * Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture, WrapMode,
+    };
+    use crate::image::Image;
+    use crate::vec3::{Color, Point3};
+
+    #[test]
+    fn test_checker() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::default();
+        let checker = CheckerTexture::new(
+            0.5,
+            Arc::new(SolidColor::new(white)),
+            Arc::new(SolidColor::new(black)),
+        );
+
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(0.1, 0.1, 0.1)), white);
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(0.6, 0.1, 0.1)), black);
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(-0.1, 0.1, 0.1)), black);
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(-0.1, -0.1, 0.1)), white);
+    }
+
+    #[test]
+    fn test_image_filtering() {
+        let pixels = (0..4).map(|i| Color::new(i as f64, 0.0, 0.0)).collect();
+        let image = Arc::new(Image::new(2, 2, pixels).unwrap());
+        let texture = ImageTexture::new(image.clone(), WrapMode::Clamp);
+        let at = |u, v| texture.value(u, v, Point3::default()).x();
+
+        // Pixel centres, with the top row of the image at v = 1.
+        assert_eq!(at(0.25, 0.75), 0.0);
+        assert_eq!(at(0.75, 0.75), 1.0);
+        assert_eq!(at(0.25, 0.25), 2.0);
+        // Halfway between pixels, and past the edge.
+        assert_eq!(at(0.5, 0.75), 0.5);
+        assert_eq!(at(0.5, 0.5), 1.5);
+        assert_eq!(at(1.5, -1.0), 3.0);
+
+        let texture = ImageTexture::new(image, WrapMode::Repeat);
+        let at = |u, v| texture.value(u, v, Point3::default()).x();
+        assert_eq!(at(1.25, 0.75), 0.0);
+        // The left edge blends with the right edge of the next tile.
+        assert_eq!(at(0.0, 0.75), 0.5);
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        let wrapped = |mode: WrapMode| (-3..6).map(|i| mode.wrap(i, 3)).collect::<Vec<_>>();
+        assert_eq!(wrapped(WrapMode::Repeat), [0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        assert_eq!(wrapped(WrapMode::Clamp), [0, 0, 0, 0, 1, 2, 2, 2, 2]);
+        assert_eq!(wrapped(WrapMode::Mirror), [2, 1, 0, 0, 1, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_noise_is_grey_and_bounded() {
+        for style in [
+            NoiseStyle::Smooth,
+            NoiseStyle::Turbulence,
+            NoiseStyle::Marble,
+        ] {
+            let texture = NoiseTexture::new(style, 4.0);
+            for i in 0..50 {
+                let c = texture.value(0.0, 0.0, Point3::new(i as f64 * 0.13, 0.5, -0.7));
+                assert!((0.0..=1.0).contains(&c.x()));
+                assert_eq!((c.x(), c.x()), (c.y(), c.z()));
+            }
+        }
+    }
+}
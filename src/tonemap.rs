@@ -0,0 +1,153 @@
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::vec3::Color;
+
+// How radiance brighter than white is brought into the displayable range.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMap {
+    // Everything brighter than white is cut off.
+    Clamp,
+    // L / (1 + L) on luminance, which keeps hues but never reaches white.
+    Reinhard,
+    // Reinhard scaled so that the `white` luminance reaches white.
+    ExtendedReinhard,
+    // Narkowicz's fit of the ACES filmic curve, per channel.
+    Aces,
+}
+
+impl ToneMap {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clamp" => Some(Self::Clamp),
+            "reinhard" => Some(Self::Reinhard),
+            "extended_reinhard" => Some(Self::ExtendedReinhard),
+            "aces" => Some(Self::Aces),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToneMapping {
+    pub operator: ToneMap,
+    // In stops, so each one doubles the brightness.
+    pub exposure: f64,
+    // The luminance shown as white by `ExtendedReinhard`.
+    pub white: f64,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMap::Clamp,
+            exposure: 0.0,
+            white: 4.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    // Maps linear radiance to linear display values in [0, 1].
+    pub fn apply(&self, c: Color) -> Color {
+        let c = c.max(Color::default()) * self.exposure.exp2();
+        let scale_luminance = |f: fn(f64, f64) -> f64| {
+            let l = c.luminance();
+            if l > 0.0 {
+                c * (f(l, self.white) / l)
+            } else {
+                c
+            }
+        };
+
+        let mapped = match self.operator {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => scale_luminance(|l, _| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard => {
+                scale_luminance(|l, white| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMap::Aces => {
+                let aces = |x: f64| (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+                Color::new(aces(c.x()), aces(c.y()), aces(c.z()))
+            }
+        };
+        mapped.min(Color::new(1.0, 1.0, 1.0))
+    }
+
+    // The opaque sRGB-encoded pixel for linear radiance `c`.
+    pub fn rgba(&self, c: Color) -> [u8; 4] {
+        let c = self.apply(c);
+        let byte = |x: f64| (255.0 * linear_to_srgb(x)).round() as u8;
+        [byte(c.x()), byte(c.y()), byte(c.z()), 255]
+    }
+}
+
+// The sRGB transfer function.
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{linear_to_srgb, ToneMap, ToneMapping};
+    use crate::vec3::Color;
+
+    fn mapping(operator: ToneMap) -> ToneMapping {
+        ToneMapping {
+            operator,
+            ..ToneMapping::default()
+        }
+    }
+
+    #[test]
+    fn test_linear_to_srgb() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-12);
+        assert!((linear_to_srgb(0.21404) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_operators() {
+        let grey = Color::new(1.0, 1.0, 1.0);
+        assert_eq!(
+            mapping(ToneMap::Clamp).rgba(grey * 8.0),
+            [255, 255, 255, 255]
+        );
+        assert_eq!(
+            mapping(ToneMap::Clamp).rgba(grey * 0.5),
+            [188, 188, 188, 255]
+        );
+
+        let reinhard = mapping(ToneMap::Reinhard).apply(grey);
+        assert!((reinhard.x() - 0.5).abs() < 1e-12);
+
+        // The white point reaches white, and anything brighter is clamped.
+        let extended = mapping(ToneMap::ExtendedReinhard);
+        assert!((extended.apply(grey * 4.0).x() - 1.0).abs() < 1e-12);
+        assert_eq!(extended.apply(grey * 100.0), grey);
+
+        let aces = mapping(ToneMap::Aces).apply(grey * 0.18);
+        assert!(aces.x() > 0.18 && aces.x() < 0.3);
+
+        // Reinhard scales luminance, so hues are kept.
+        let red = mapping(ToneMap::Reinhard).apply(Color::new(1.5, 0.5, 0.0));
+        assert!((red.x() / red.y() - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let brighter = ToneMapping {
+            exposure: 1.0,
+            ..ToneMapping::default()
+        };
+        let c = Color::new(0.25, 0.125, 0.0);
+        assert_eq!(brighter.apply(c), c * 2.0);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hittable, Intersection};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+const BBOX_PADDING: f64 = 1e-4;
+
+// Möller–Trumbore ray/triangle test. Returns the distance along the ray and
+// the barycentric weights of the second and third vertices.
+pub fn intersect(
+    r: &Ray,
+    [a, b, c]: [Point3; 3],
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let (edge1, edge2) = (b - a, c - a);
+    let pvec = r.direction().cross(edge2);
+    let det = edge1.dot(pvec);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = r.origin() - a;
+    let u = tvec.dot(pvec) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let qvec = tvec.cross(edge1);
+    let v = r.direction().dot(qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(qvec) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, u, v))
+}
+
+pub fn bounding_box([a, b, c]: [Point3; 3]) -> Aabb {
+    Aabb::new(a.min(b).min(c), a.max(b).max(c)).padded(BBOX_PADDING)
+}
+
+pub fn area([a, b, c]: [Point3; 3]) -> f64 {
+    0.5 * (b - a).cross(c - a).length()
+}
+
+// A point spread uniformly over the triangle by `u`.
+pub fn sample_point([a, b, c]: [Point3; 3], (u1, u2): (f64, f64)) -> Point3 {
+    let s = u1.sqrt();
+    a + (1.0 - s) * (b - a) + u2 * s * (c - a)
+}
+
+// The density per solid angle of the point `t` along `r`, on a surface with
+// unit normal `normal` sampled uniformly over `area`.
+pub fn solid_angle_pdf(r: &Ray, t: f64, normal: Vec3, area: f64) -> f64 {
+    let dir = r.direction();
+    let cosine = normal.dot(dir).abs() / dir.length();
+    if cosine < 1e-12 {
+        return 0.0;
+    }
+    t * t * dir.length_squared() / (cosine * area)
+}
+
+// The unit direction from `origin` to `p`, unless they are the same point.
+pub fn towards(origin: Point3, p: Point3) -> Option<Vec3> {
+    let dir = p - origin;
+    if dir.near_zero() {
+        None
+    } else {
+        Some(dir.unit())
+    }
+}
+
+pub struct Triangle<M: Material> {
+    vertices: [Point3; 3],
+    mat: M,
+}
+
+impl<M: Material> Triangle<M> {
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: M) -> Self {
+        Self {
+            vertices: [a, b, c],
+            mat,
+        }
+    }
+}
+
+impl<M: Material + 'static> Hittable for Triangle<M> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'_>> {
+        let (t, u, v) = intersect(r, self.vertices, t_min, t_max)?;
+
+        let [a, b, c] = self.vertices;
+        let outward_normal = (b - a).cross(c - a).unit();
+        let (normal, front_face) = face_normal(r, outward_normal, outward_normal);
+
+        Some(Intersection::new(
+            r.at(t),
+            normal,
+            &self.mat,
+            t,
+            (u, v),
+            front_face,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        bounding_box(self.vertices)
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        intersect(r, self.vertices, t_min, t_max).is_some()
+    }
+
+    fn sample(&self, origin: Point3, u: (f64, f64)) -> Option<Vec3> {
+        towards(origin, sample_point(self.vertices, u))
+    }
+
+    fn pdf(&self, origin: Point3, dir: Vec3) -> f64 {
+        let r = Ray::new(origin, dir);
+        let Some((t, _, _)) = intersect(&r, self.vertices, 0.001, f64::INFINITY) else {
+            return 0.0;
+        };
+        let [a, b, c] = self.vertices;
+        let normal = (b - a).cross(c - a).unit();
+        solid_angle_pdf(&r, t, normal, area(self.vertices))
+    }
+}
+
+// Orients `shading` to the side of the surface the ray arrived from, deciding
+// the side by the geometric normal so interpolated normals can't flip it.
+pub fn face_normal(r: &Ray, geometric: Vec3, shading: Vec3) -> (Vec3, bool) {
+    let shading = if shading.dot(geometric) < 0.0 {
+        -shading
+    } else {
+        shading
+    };
+
+    if r.direction().dot(geometric) < 0.0 {
+        (shading, true)
+    } else {
+        (-shading, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Triangle;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn triangle() -> Triangle<Lambertian> {
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let tri = triangle();
+        let r = Ray::new(Point3::new(0.25, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let i = tri.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((i.t - 2.0).abs() < 1e-12);
+        assert!((i.u - 0.25).abs() < 1e-12 && (i.v - 0.5).abs() < 1e-12);
+        assert_eq!(i.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(i.front_face);
+
+        let back = Ray::new(Point3::new(0.25, 0.25, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        let i = tri.hit(&back, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(i.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert!(!i.front_face);
+    }
+
+    #[test]
+    fn test_miss() {
+        let t = triangle();
+        let outside = Ray::new(Point3::new(0.75, 0.75, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let parallel = Ray::new(Point3::new(-1.0, 0.25, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let short = Ray::new(Point3::new(0.25, 0.25, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(t.hit(&outside, 0.001, f64::INFINITY).is_none());
+        assert!(t.hit(&parallel, 0.001, f64::INFINITY).is_none());
+        assert!(t.hit(&short, 0.001, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_bounding_box_is_hit_when_flat() {
+        let r = Ray::new(Point3::new(0.25, 0.25, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle().bounding_box().hit(&r, 0.001, f64::INFINITY));
+    }
+}
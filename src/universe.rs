@@ -152,7 +152,7 @@ impl fmt::Display for Universe {
                 let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
                 write!(f, "{symbol}")?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
@@ -1,3 +1,6 @@
+use js_sys::Uint8Array;
+use wasm_bindgen::{JsCast, JsValue};
+
 macro_rules! log {
     ( $( $t:tt )* ) => {
         web_sys::console::log_1(&format!( $( $t )* ).into());
@@ -16,3 +19,15 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+// Reads text passed from JS either as a string or as UTF-8 bytes.
+pub fn text_from_js(value: &JsValue) -> Result<String, JsValue> {
+    if let Some(s) = value.as_string() {
+        return Ok(s);
+    }
+
+    match value.dyn_ref::<Uint8Array>() {
+        Some(bytes) => String::from_utf8(bytes.to_vec()).map_err(|e| JsValue::from(format!("{e}"))),
+        None => Err(JsValue::from("expected a string or Uint8Array")),
+    }
+}
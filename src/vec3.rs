@@ -1,6 +1,9 @@
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub};
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
 
-use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rand::Rng;
+
+use crate::sampler::Sampler;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3(f64, f64, f64);
@@ -9,7 +12,7 @@ pub type Color = Vec3;
 pub type Point3 = Vec3;
 
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
         Self(x, y, z)
     }
 
@@ -53,15 +56,27 @@ impl Vec3 {
             self.0 * rhs.1 - self.1 * rhs.0,
         )
     }
+
+    pub fn min(&self, rhs: Self) -> Self {
+        Self(self.0.min(rhs.0), self.1.min(rhs.1), self.2.min(rhs.2))
+    }
+
+    pub fn max(&self, rhs: Self) -> Self {
+        Self(self.0.max(rhs.0), self.1.max(rhs.1), self.2.max(rhs.2))
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.0.max(self.1).max(self.2)
+    }
+
+    // Perceived brightness of a linear Rec. 709 colour.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
 }
 
 impl Vec3 {
-    pub fn random(min: f64, max: f64) -> Self {
-        let mut rng = match SmallRng::from_rng(rand::thread_rng()) {
-            Ok(rng) => rng,
-            _ => return Self::default(),
-        };
-
+    pub fn random<R: Rng + ?Sized>(rng: &mut R, min: f64, max: f64) -> Self {
         Self(
             rng.gen_range(min..max),
             rng.gen_range(min..max),
@@ -69,27 +84,23 @@ impl Vec3 {
         )
     }
 
-    pub fn random_in_unit_sphere() -> Self {
-        loop {
-            let p = Self::random(-1.0, 1.0);
-            if p.length_squared() < 1.0 {
-                return p;
-            }
-        }
+    // The helpers below map samples onto their shapes directly rather than
+    // by rejection, so each takes a fixed number of samples.
+    pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Self {
+        sampler.get_1d().cbrt() * Self::random_unit_vector(sampler)
     }
 
-    pub fn random_in_unit_disk() -> Self {
-        loop {
-            let mut p = Self::random(-1.0, 1.0);
-            p.2 = 0.0;
-            if p.length_squared() < 1.0 {
-                return p;
-            }
-        }
+    pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Self {
+        let (u1, u2) = sampler.get_2d();
+        let (r, phi) = (u1.sqrt(), 2.0 * PI * u2);
+        Self(r * phi.cos(), r * phi.sin(), 0.0)
     }
 
-    pub fn random_unit_vector() -> Self {
-        Self::random_in_unit_sphere().unit()
+    pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Self {
+        let (u1, u2) = sampler.get_2d();
+        let z = 1.0 - 2.0 * u1;
+        let (r, phi) = ((1.0 - z * z).max(0.0).sqrt(), 2.0 * PI * u2);
+        Self(r * phi.cos(), r * phi.sin(), z)
     }
 
     pub fn reflect(self, n: Self) -> Self {
@@ -142,6 +153,19 @@ impl DivAssign<f64> for Vec3 {
     }
 }
 
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            _ => panic!("Vec3 axis out of range: {axis}"),
+        }
+    }
+}
+
 impl Mul for Vec3 {
     type Output = Self;
 
@@ -190,21 +214,10 @@ impl Sub for Vec3 {
     }
 }
 
-impl Into<Vec<u8>> for Color {
-    fn into(self) -> Vec<u8> {
-        let (r, g, b) = (
-            256.0 * self.0.sqrt().clamp(0.0, 0.999),
-            256.0 * self.1.sqrt().clamp(0.0, 0.999),
-            256.0 * self.2.sqrt().clamp(0.0, 0.999),
-        );
-
-        vec![r as u8, g as u8, b as u8, 255]
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::Vec3;
+    use crate::sampler::IndependentSampler;
 
     #[test]
     fn test_length() {
@@ -240,4 +253,15 @@ mod tests {
         );
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn test_random_shapes() {
+        let mut sampler = IndependentSampler::new(0);
+        for _ in 0..100 {
+            assert!((Vec3::random_unit_vector(&mut sampler).length() - 1.0).abs() < 1e-12);
+            assert!(Vec3::random_in_unit_sphere(&mut sampler).length() < 1.0);
+            let p = Vec3::random_in_unit_disk(&mut sampler);
+            assert!(p.length() < 1.0 && p.2 == 0.0);
+        }
+    }
 }
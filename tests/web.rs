@@ -9,7 +9,14 @@ wasm_bindgen_test_configure!(run_in_browser);
 
 #[wasm_bindgen_test]
 pub fn test_render() {
-    let got = wasm_raytracer::render(256, 256).unwrap();
+    let got = wasm_raytracer::render(256, 256, 0);
     let want = 256 * 256 * 4;
     assert_eq!(got.length(), want);
 }
+
+#[wasm_bindgen_test]
+pub fn test_render_is_seeded() {
+    let a = wasm_raytracer::render(32, 32, 1).to_vec();
+    let b = wasm_raytracer::render(32, 32, 1).to_vec();
+    assert_eq!(a, b);
+}